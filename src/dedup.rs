@@ -0,0 +1,146 @@
+use crate::{Access, Device, EnumeratedValues, Protection, ResolvedPeripheral, WriteConstraint};
+use eyre::Result;
+use indexmap::IndexMap;
+
+type Shape<'a> = Vec<RegisterShape<'a>>;
+
+type RegisterShape<'a> =
+    (&'a [String], u32, u32, u32, Access, Option<Protection>, bool, Vec<FieldShape<'a>>);
+
+type FieldShape<'a> =
+    (&'a str, u32, u32, Access, Option<&'a WriteConstraint>, &'a [EnumeratedValues], bool);
+
+impl Device {
+    /// Returns groups of structurally identical peripherals.
+    ///
+    /// Peripherals are identical if their registers have the same paths,
+    /// offsets, sizes, reset values, access rights, protection levels, and
+    /// fields. Descriptions
+    /// are ignored. Peripheral arrays are not considered. Each group has at
+    /// least two members, listed in the order of the description.
+    pub fn identical_periphs(&self) -> Result<Vec<Vec<String>>> {
+        let device = self.resolve()?;
+        let mut groups = Vec::<(Shape<'_>, Vec<String>)>::new();
+        for peripheral in &device.peripherals {
+            if self.peripherals.get(&peripheral.name).map_or(true, |p| p.dim.is_some()) {
+                continue;
+            }
+            let shape = shape(peripheral);
+            if let Some((_, names)) = groups.iter_mut().find(|(other, _)| *other == shape) {
+                names.push(peripheral.name.clone());
+            } else {
+                groups.push((shape, vec![peripheral.name.clone()]));
+            }
+        }
+        Ok(groups.into_iter().map(|(_, names)| names).filter(|names| names.len() > 1).collect())
+    }
+
+    /// Rewrites structurally identical peripherals to inherit registers from
+    /// the first non-derived peripheral of their group with `derived_from`.
+    ///
+    /// Peripherals which other peripherals are derived from are kept intact.
+    /// Returns the number of rewritten peripherals.
+    pub fn dedup_periphs(&mut self) -> Result<usize> {
+        let parents = self
+            .peripherals
+            .values()
+            .filter_map(|peripheral| peripheral.derived_from.clone())
+            .collect::<Vec<_>>();
+        let mut count = 0;
+        for names in self.identical_periphs()? {
+            let Some(leader) =
+                names.iter().find(|name| self.peripherals[*name].derived_from.is_none()).cloned()
+            else {
+                continue;
+            };
+            for name in &names {
+                let peripheral = &mut self.peripherals[name];
+                if *name == leader
+                    || parents.contains(name)
+                    || peripheral.derived_from.as_ref() == Some(&leader)
+                {
+                    continue;
+                }
+                peripheral.derived_from = Some(leader.clone());
+                peripheral.size = None;
+                peripheral.reset_value = None;
+                peripheral.access = None;
+                peripheral.registers = IndexMap::new();
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+fn shape(peripheral: &ResolvedPeripheral) -> Shape<'_> {
+    peripheral
+        .registers
+        .iter()
+        .map(|register| {
+            let fields = register
+                .fields
+                .iter()
+                .map(|field| {
+                    (
+                        field.name.as_str(),
+                        field.bit_offset,
+                        field.bit_width,
+                        field.access,
+                        field.write_constraint.as_ref(),
+                        field.enumerated_values.as_slice(),
+                        field.force_bits,
+                    )
+                })
+                .collect();
+            (
+                register.path.as_slice(),
+                register.address - peripheral.base_address,
+                register.size,
+                register.reset_value,
+                register.access,
+                register.protection,
+                register.alternate,
+                fields,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Device;
+
+    fn device(protection: &str) -> Device {
+        quick_xml::de::from_str(&format!(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue><peripherals>
+                <peripheral><name>UART1</name><description>u</description>
+                    <baseAddress>0x1000</baseAddress>
+                    <registers>
+                        <register><name>DR</name><description>r</description>
+                            <addressOffset>0</addressOffset>
+                            <protection>s</protection></register>
+                    </registers>
+                </peripheral>
+                <peripheral><name>UART2</name><description>u</description>
+                    <baseAddress>0x2000</baseAddress>
+                    <registers>
+                        <register><name>DR</name><description>r</description>
+                            <addressOffset>0</addressOffset>
+                            <protection>{protection}</protection></register>
+                    </registers>
+                </peripheral>
+            </peripherals></device>"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn protection_is_part_of_shape() {
+        assert_eq!(device("s").identical_periphs().unwrap(), [["UART1", "UART2"]]);
+        assert!(device("n").identical_periphs().unwrap().is_empty());
+        let mut device = device("n");
+        assert_eq!(device.dedup_periphs().unwrap(), 0);
+        assert_eq!(device.peripherals["UART2"].derived_from, None);
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{de, Deserialize, Deserializer};
+
+/// A set of named values for a field.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnumeratedValues {
+    /// Identifier for the whole enumeration section.
+    pub name: Option<String>,
+    /// Identifies which access operations the enumeration applies to.
+    pub usage: Option<Usage>,
+    /// Named values of the field.
+    #[serde(default, rename = "enumeratedValue")]
+    pub values: Vec<EnumeratedValue>,
+}
+
+/// A named value of a field.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct EnumeratedValue {
+    /// String describing the semantics of the value.
+    pub name: String,
+    /// Extended string describing the value.
+    #[serde(default)]
+    pub description: String,
+    /// The value with don't care bits cleared.
+    #[serde(default, deserialize_with = "deserialize_value")]
+    pub value: Option<EnumValue>,
+    /// Defines the name and description for all other values that are not
+    /// listed explicitly.
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+/// A numeric value of an enumerated value with optional don't care bits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EnumValue {
+    /// The value with don't care bits cleared.
+    pub value: u32,
+    /// Mask of don't care bits.
+    pub dont_care: u32,
+}
+
+/// Access operations an enumeration applies to.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Usage {
+    /// The enumeration applies to read operations.
+    Read,
+    /// The enumeration applies to write operations.
+    Write,
+    /// The enumeration applies to both read and write operations.
+    ReadWrite,
+}
+
+impl EnumeratedValues {
+    /// Returns `true` if the enumeration applies to read operations.
+    pub fn is_readable(&self) -> bool {
+        !matches!(self.usage, Some(Usage::Write))
+    }
+
+    /// Returns `true` if the enumeration applies to write operations.
+    pub fn is_writable(&self) -> bool {
+        !matches!(self.usage, Some(Usage::Read))
+    }
+
+    /// Returns the named value matching `value`, falling back to the default
+    /// value.
+    pub fn find(&self, value: u32) -> Option<&EnumeratedValue> {
+        self.values
+            .iter()
+            .find(|v| v.value.map_or(false, |v| v.matches(value)))
+            .or_else(|| self.values.iter().find(|v| v.is_default))
+    }
+}
+
+impl EnumValue {
+    /// Returns `true` if `value` matches ignoring don't care bits.
+    pub fn matches(self, value: u32) -> bool {
+        value & !self.dont_care == self.value
+    }
+}
+
+fn deserialize_value<'de, D>(deserializer: D) -> Result<Option<EnumValue>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?.map_or(Ok(None), |s| {
+        parse_value(s.trim())
+            .map(Some)
+            .ok_or_else(|| de::Error::custom(format!("invalid enumerated value `{s}`")))
+    })
+}
+
+fn parse_value(src: &str) -> Option<EnumValue> {
+    let binary =
+        src.strip_prefix('#').or_else(|| src.strip_prefix("0b")).or_else(|| src.strip_prefix("0B"));
+    if let Some(binary) = binary {
+        let mut value = EnumValue::default();
+        for digit in binary.chars() {
+            value.value <<= 1;
+            value.dont_care <<= 1;
+            match digit {
+                '0' => {}
+                '1' => value.value |= 1,
+                'x' | 'X' => value.dont_care |= 1,
+                _ => return None,
+            }
+        }
+        Some(value)
+    } else {
+        super::parse_int(src).ok().map(|value| EnumValue { value, dont_care: 0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_value, EnumValue, EnumeratedValues};
+
+    #[test]
+    fn binary_values_with_dont_care_bits() {
+        assert_eq!(parse_value("#1x0"), Some(EnumValue { value: 0b100, dont_care: 0b010 }));
+        assert_eq!(parse_value("0b1X"), Some(EnumValue { value: 0b10, dont_care: 0b01 }));
+        assert_eq!(parse_value("0B01"), Some(EnumValue { value: 0b01, dont_care: 0 }));
+        assert_eq!(parse_value("0x1F"), Some(EnumValue { value: 0x1F, dont_care: 0 }));
+        assert_eq!(parse_value("#12"), None);
+        let value = parse_value("#1x0").unwrap();
+        assert!(value.matches(0b100));
+        assert!(value.matches(0b110));
+        assert!(!value.matches(0b101));
+    }
+
+    #[test]
+    fn find_falls_back_to_default() {
+        let values: EnumeratedValues = quick_xml::de::from_str(
+            r"<enumeratedValues>
+                <enumeratedValue><name>LOW</name><value>#0x</value></enumeratedValue>
+                <enumeratedValue><name>OTHER</name><isDefault>true</isDefault></enumeratedValue>
+            </enumeratedValues>",
+        )
+        .unwrap();
+        assert_eq!(values.find(0).map(|v| v.name.as_str()), Some("LOW"));
+        assert_eq!(values.find(1).map(|v| v.name.as_str()), Some("LOW"));
+        assert_eq!(values.find(2).map(|v| v.name.as_str()), Some("OTHER"));
+        assert_eq!(values.values[1].value, None);
+    }
+}
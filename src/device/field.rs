@@ -1,5 +1,7 @@
 use super::access::{Access, AccessWrapper};
-use super::{deserialize_int_opt, parse_int};
+use super::enumerated_value::EnumeratedValues;
+use super::{deserialize_int, deserialize_int_opt, parse_int};
+use eyre::{eyre, Result};
 use serde::de::Deserializer;
 use serde::{de, Deserialize};
 use std::num::ParseIntError;
@@ -41,11 +43,44 @@ pub struct Field {
     /// The access type.
     #[serde(default, with = "AccessWrapper")]
     pub access: Option<Access>,
+    /// Constraints for writing values to the field.
+    pub write_constraint: Option<WriteConstraint>,
+    /// Sets of named values of the field.
+    #[serde(default)]
+    pub enumerated_values: Vec<EnumeratedValues>,
     /// Force the field to have multiple bits interface.
     #[serde(skip)]
     pub force_bits: bool,
 }
 
+/// Constraints for writing values to a field.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteConstraint {
+    /// Only the last read value can be written.
+    #[serde(default)]
+    pub write_as_read: bool,
+    /// Only the values listed in the enumerated values can be written.
+    #[serde(default)]
+    pub use_enumerated_values: bool,
+    /// The range of values that can be written.
+    pub range: Option<WriteConstraintRange>,
+}
+
+/// A range of values that can be written to a field.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteConstraintRange {
+    /// The smallest number that can be written.
+    #[serde(deserialize_with = "deserialize_int")]
+    pub minimum: u32,
+    /// The largest number that can be written.
+    #[serde(deserialize_with = "deserialize_int")]
+    pub maximum: u32,
+}
+
 impl Field {
     /// Returns the position of the least significant bit of the field within
     /// the register.
@@ -63,6 +98,27 @@ impl Field {
             .or_else(|| self.bit_range.as_ref().map(|r| *r.end() - *r.start() + 1))
             .expect("bit-range is missing")
     }
+
+    /// Returns the position of the least significant bit and the bit-width of
+    /// the field, or an error if the bit-range is missing or malformed.
+    pub(crate) fn try_bit_range(&self) -> Result<(u32, u32)> {
+        let missing = || eyre!("field `{}` is missing a bit-range", self.name);
+        let malformed = || eyre!("field `{}` has a malformed bit-range", self.name);
+        let offset = self
+            .bit_offset
+            .or(self.lsb)
+            .or_else(|| self.bit_range.as_ref().map(|r| *r.start()))
+            .ok_or_else(missing)?;
+        let width = match (self.bit_width, self.lsb.zip(self.msb), &self.bit_range) {
+            (Some(width), _, _) => width,
+            (None, Some((lsb, msb)), _) => msb.checked_sub(lsb).ok_or_else(malformed)? + 1,
+            (None, None, Some(range)) => {
+                range.end().checked_sub(*range.start()).ok_or_else(malformed)? + 1
+            }
+            (None, None, None) => return Err(missing()),
+        };
+        Ok((offset, width))
+    }
 }
 
 fn deserialize_bit_range<'de, D>(deserializer: D) -> Result<Option<RangeInclusive<u32>>, D::Error>
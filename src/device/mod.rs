@@ -1,14 +1,22 @@
 mod access;
+mod enumerated_value;
 mod field;
 mod peripheral;
+mod protection;
 mod register;
 
 pub use self::access::Access;
 use self::access::AccessWrapper;
-pub use self::field::Field;
-pub use self::peripheral::Peripheral;
+pub use self::enumerated_value::{EnumValue, EnumeratedValue, EnumeratedValues, Usage};
+pub use self::field::{Field, WriteConstraint, WriteConstraintRange};
+pub use self::peripheral::{AddressBlock, Peripheral};
+pub use self::protection::Protection;
+use self::protection::ProtectionWrapper;
 pub use self::register::Register;
-pub(crate) use self::register::{Cluster, RegisterTree};
+pub(crate) use self::register::{
+    block_protection, tree_rekey, tree_remove_regs, tree_select_regs, Cluster, RegisterTree,
+};
+use crate::glob::glob_match;
 use eyre::Result;
 use indexmap::IndexMap;
 use serde::{de, Deserialize, Deserializer};
@@ -30,6 +38,9 @@ pub struct Device {
     /// Default access rights for all registers.
     #[serde(default, with = "AccessWrapper")]
     pub access: Option<Access>,
+    /// Default protection level for all registers.
+    #[serde(default, with = "ProtectionWrapper")]
+    pub protection: Option<Protection>,
     #[serde(default, with = "PeripheralsWrapper")]
     pub(crate) peripherals: IndexMap<String, Peripheral>,
 }
@@ -43,7 +54,14 @@ struct PeripheralsWrapper {
 impl Device {
     /// Creates a new empty device definition.
     pub fn new(name: String) -> Self {
-        Self { name, size: None, reset_value: None, access: None, peripherals: IndexMap::new() }
+        Self {
+            name,
+            size: None,
+            reset_value: None,
+            access: None,
+            protection: None,
+            peripherals: IndexMap::new(),
+        }
     }
 
     /// Returns an iterator over all peripheral names.
@@ -56,6 +74,59 @@ impl Device {
         self.peripherals.get_mut(name).unwrap()
     }
 
+    /// Returns an iterator over mutable references to the peripherals matching
+    /// the glob pattern `pattern`.
+    pub fn select_periphs(
+        &mut self,
+        pattern: &str,
+    ) -> impl ExactSizeIterator<Item = &mut Peripheral> + '_ {
+        self.peripherals
+            .values_mut()
+            .filter(|peripheral| glob_match(pattern, &peripheral.name))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns an iterator over mutable references to the registers matching
+    /// the glob pattern `pattern` in the `PERIPHERAL/CLUSTER/REGISTER` form.
+    pub fn select_regs(
+        &mut self,
+        pattern: &str,
+    ) -> impl ExactSizeIterator<Item = &mut Register> + '_ {
+        let (peripheral, path) = pattern.split_once('/').unwrap_or((pattern, ""));
+        let path = path.split('/').collect::<Vec<_>>();
+        let mut registers = Vec::new();
+        for peripheral in self.select_periphs(peripheral) {
+            tree_select_regs(&mut peripheral.registers, &path, &mut registers);
+        }
+        registers.into_iter()
+    }
+
+    /// Returns an iterator over mutable references to the fields matching the
+    /// glob pattern `pattern` in the `PERIPHERAL/CLUSTER/REGISTER/FIELD` form.
+    pub fn select_fields(
+        &mut self,
+        pattern: &str,
+    ) -> impl ExactSizeIterator<Item = &mut Field> + '_ {
+        let (path, field) = pattern.rsplit_once('/').unwrap_or(("", pattern));
+        self.select_regs(path)
+            .flat_map(|register| register.select_fields(field))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns peripherals grouped by `groupName` in the order of the
+    /// description. Peripherals without a group are omitted.
+    pub fn periph_groups(&self) -> Result<IndexMap<&str, Vec<&Peripheral>>> {
+        let mut groups = IndexMap::<_, Vec<_>>::new();
+        for peripheral in self.peripherals.values() {
+            if let Some(group_name) = peripheral.group_name(peripheral.derived_from(self)?) {
+                groups.entry(group_name).or_default().push(peripheral);
+            }
+        }
+        Ok(groups)
+    }
+
     /// Inserts a new peripheral `peripheral`.
     pub fn add_periph(&mut self, peripheral: Peripheral) {
         self.peripherals.insert(peripheral.name.clone(), peripheral);
@@ -81,7 +152,10 @@ impl PeripheralsWrapper {
     {
         let mut map = IndexMap::new();
         for peripheral in <Self as Deserialize>::deserialize(deserializer)?.values {
-            map.insert(peripheral.name.clone(), peripheral);
+            let name = peripheral.name.clone();
+            if let Some(previous) = map.insert(name.clone(), peripheral) {
+                map[&name].duplicates = previous.duplicates + 1;
+            }
         }
         Ok(map)
     }
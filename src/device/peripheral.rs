@@ -1,5 +1,8 @@
 use super::access::{Access, AccessWrapper};
-use super::register::{tree_reg, tree_remove_reg, Register, RegisterTree};
+use super::protection::{Protection, ProtectionWrapper};
+use super::register::{
+    tree_insert, tree_reg, tree_remove_reg, tree_select_regs, Register, RegisterTree,
+};
 use super::{deserialize_int, deserialize_int_opt, Device};
 use eyre::{eyre, Result};
 use indexmap::IndexMap;
@@ -27,6 +30,8 @@ pub struct Peripheral {
     /// A peripheral redefining an address block needs to specify the name of
     /// the peripheral that is listed first in the description.
     pub alternate_peripheral: Option<String>,
+    /// The name of the group the peripheral belongs to.
+    pub group_name: Option<String>,
     /// Lowest address reserved or used by the peripheral.
     #[serde(deserialize_with = "deserialize_int")]
     pub base_address: u32,
@@ -39,10 +44,34 @@ pub struct Peripheral {
     /// Default access rights for all registers in the peripheral.
     #[serde(default, with = "AccessWrapper")]
     pub access: Option<Access>,
+    /// Default protection level for all registers in the peripheral.
+    #[serde(default, with = "ProtectionWrapper")]
+    pub protection: Option<Protection>,
+    /// Address ranges reserved or used by the peripheral.
+    #[serde(default, rename = "addressBlock")]
+    pub address_blocks: Vec<AddressBlock>,
     #[serde(default, with = "RegistersWrapper")]
     pub(crate) registers: IndexMap<String, RegisterTree>,
     #[serde(skip)]
     pub(crate) variants: Vec<String>,
+    #[serde(skip)]
+    pub(crate) duplicates: usize,
+}
+
+/// An address range of a peripheral.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBlock {
+    /// The start address of the block relative to the peripheral base address.
+    #[serde(deserialize_with = "deserialize_int")]
+    pub offset: u32,
+    /// The number of addresses covered by the block.
+    #[serde(deserialize_with = "deserialize_int")]
+    pub size: u32,
+    /// The protection level for all registers in the block.
+    #[serde(default, with = "ProtectionWrapper")]
+    pub protection: Option<Protection>,
 }
 
 #[derive(Deserialize)]
@@ -57,6 +86,21 @@ impl Peripheral {
         tree_reg(&mut self.registers, path)
     }
 
+    /// Returns an iterator over mutable references to the registers matching
+    /// the glob pattern `pattern` in the `CLUSTER/REGISTER` form.
+    pub fn select_regs(
+        &mut self,
+        pattern: &str,
+    ) -> impl ExactSizeIterator<Item = &mut Register> + '_ {
+        let mut registers = Vec::new();
+        tree_select_regs(
+            &mut self.registers,
+            &pattern.split('/').collect::<Vec<_>>(),
+            &mut registers,
+        );
+        registers.into_iter()
+    }
+
     /// Adds a new register `register`.
     pub fn add_reg(&mut self, register: Register) {
         self.registers.insert(register.name.clone(), RegisterTree::Register(register));
@@ -87,6 +131,23 @@ impl Peripheral {
         })
     }
 
+    pub(crate) fn group_name<'a>(&'a self, parent: Option<&'a Peripheral>) -> Option<&'a str> {
+        self.group_name
+            .as_ref()
+            .or_else(|| parent.and_then(|parent| parent.group_name.as_ref()))
+            .map(String::as_str)
+    }
+
+    pub(crate) fn address_blocks<'a>(
+        &'a self,
+        parent: Option<&'a Peripheral>,
+    ) -> &'a [AddressBlock] {
+        match parent {
+            Some(parent) if self.address_blocks.is_empty() => &parent.address_blocks,
+            _ => &self.address_blocks,
+        }
+    }
+
     pub(crate) fn description<'a>(&'a self, parent: Option<&'a Peripheral>) -> Option<&'a str> {
         self.description
             .as_ref()
@@ -102,11 +163,7 @@ impl RegistersWrapper {
     {
         let mut map = IndexMap::new();
         for tree in <Self as Deserialize>::deserialize(deserializer)?.values {
-            let name = match &tree {
-                RegisterTree::Register(register) => register.name.clone(),
-                RegisterTree::Cluster(cluster) => cluster.name.clone(),
-            };
-            map.insert(name, tree);
+            tree_insert(&mut map, tree);
         }
         Ok(map)
     }
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Deserializer};
+
+/// Predefined security and privilege protection levels.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum Protection {
+    /// Secure permission required for access.
+    #[serde(rename = "s")]
+    Secure,
+    /// Non-secure or secure permission required for access.
+    #[serde(rename = "n")]
+    NonSecure,
+    /// Privileged permission required for access.
+    #[serde(rename = "p")]
+    Privileged,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ProtectionWrapper {
+    #[serde(rename = "$value")]
+    value: Option<Protection>,
+}
+
+impl ProtectionWrapper {
+    pub(crate) fn deserialize<'de, D>(deserializer: D) -> Result<Option<Protection>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(<Self as Deserialize>::deserialize(deserializer)?.value)
+    }
+}
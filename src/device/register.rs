@@ -1,10 +1,14 @@
 use super::access::{Access, AccessWrapper};
 use super::field::Field;
-use super::peripheral::Peripheral;
+use super::peripheral::{AddressBlock, Peripheral};
+use super::protection::{Protection, ProtectionWrapper};
 use super::{deserialize_int, deserialize_int_opt, Device};
-use eyre::{eyre, Result};
+use crate::glob::glob_match;
+use eyre::{bail, eyre, Result};
 use indexmap::IndexMap;
 use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::mem;
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,6 +18,7 @@ pub(crate) enum RegisterTree {
 }
 
 #[non_exhaustive]
+#[allow(clippy::struct_field_names)]
 #[derive(Clone, Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct Cluster {
@@ -35,10 +40,15 @@ pub(crate) struct Cluster {
     /// Cluster address relative to the <baseAddress> of the peripheral.
     #[serde(deserialize_with = "deserialize_int")]
     pub address_offset: u32,
+    /// Default protection level for all registers in the cluster.
+    #[serde(default, with = "ProtectionWrapper")]
+    pub protection: Option<Protection>,
     #[serde(default, deserialize_with = "deserialize_registers")]
     pub(crate) register: IndexMap<String, RegisterTree>,
     #[serde(skip)]
     pub(crate) variants: Vec<String>,
+    #[serde(skip)]
+    pub(crate) duplicates: usize,
 }
 
 /// The description of a register.
@@ -71,6 +81,9 @@ pub struct Register {
     /// The access rights for the register.
     #[serde(default, with = "AccessWrapper")]
     pub access: Option<Access>,
+    /// The protection level for the register.
+    #[serde(default, with = "ProtectionWrapper")]
+    pub protection: Option<Protection>,
     /// The default value for the register at RESET.
     #[serde(default, deserialize_with = "deserialize_int_opt")]
     pub reset_value: Option<u32>,
@@ -78,6 +91,8 @@ pub struct Register {
     pub(crate) fields: Vec<Field>,
     #[serde(skip)]
     pub(crate) variants: Vec<String>,
+    #[serde(skip)]
+    pub(crate) duplicates: usize,
 }
 
 #[derive(Deserialize)]
@@ -92,6 +107,19 @@ impl Register {
         self.fields.iter_mut().find(|field| field.name == name).unwrap()
     }
 
+    /// Returns an iterator over mutable references to the fields matching the
+    /// glob pattern `pattern`.
+    pub fn select_fields(
+        &mut self,
+        pattern: &str,
+    ) -> impl ExactSizeIterator<Item = &mut Field> + '_ {
+        self.fields
+            .iter_mut()
+            .filter(|field| glob_match(pattern, &field.name))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
     /// Adds a new field `field`.
     pub fn add_field(&mut self, field: Field) {
         self.fields.push(field);
@@ -164,9 +192,49 @@ impl Register {
                     .flatten()
             })
     }
+
+    pub(crate) fn protection(
+        &self,
+        device: &Device,
+        peripheral: &Peripheral,
+        parent: Option<&Peripheral>,
+        clusters: &[&Cluster],
+    ) -> Option<Protection> {
+        self.protection
+            .or_else(|| clusters.iter().rev().find_map(|cluster| cluster.protection))
+            .or_else(|| {
+                let offset = clusters.iter().map(|cluster| cluster.address_offset).sum::<u32>()
+                    + self.address_offset;
+                block_protection(peripheral.address_blocks(parent), offset)
+            })
+            .or(peripheral.protection)
+            .or_else(|| parent.and_then(|peripheral| peripheral.protection))
+            .or(device.protection)
+    }
+}
+
+pub(crate) fn block_protection(blocks: &[AddressBlock], offset: u32) -> Option<Protection> {
+    blocks
+        .iter()
+        .filter(|block| (block.offset..block.offset + block.size).contains(&offset))
+        .find_map(|block| block.protection)
 }
 
 impl RegisterTree {
+    pub(crate) fn duplicates(&self) -> usize {
+        match self {
+            RegisterTree::Register(register) => register.duplicates,
+            RegisterTree::Cluster(cluster) => cluster.duplicates,
+        }
+    }
+
+    fn duplicates_mut(&mut self) -> &mut usize {
+        match self {
+            RegisterTree::Register(register) => &mut register.duplicates,
+            RegisterTree::Cluster(cluster) => &mut cluster.duplicates,
+        }
+    }
+
     #[track_caller]
     pub(crate) fn unwrap_register_ref(&self) -> &Register {
         match self {
@@ -219,9 +287,21 @@ impl RegisterTree {
     }
 }
 
-pub(crate) fn tree_reg<'a, 'b>(
+/// Inserts `node` into `tree`, replacing an element with the same name and
+/// counting the replaced duplicates.
+pub(crate) fn tree_insert(tree: &mut IndexMap<String, RegisterTree>, node: RegisterTree) {
+    let name = match &node {
+        RegisterTree::Register(register) => register.name.clone(),
+        RegisterTree::Cluster(cluster) => cluster.name.clone(),
+    };
+    if let Some(previous) = tree.insert(name.clone(), node) {
+        *tree[&name].duplicates_mut() = previous.duplicates() + 1;
+    }
+}
+
+pub(crate) fn tree_reg<'a>(
     tree: &'a mut IndexMap<String, RegisterTree>,
-    path: &'b str,
+    path: &str,
 ) -> &'a mut Register {
     let mut path = path.splitn(2, '/');
     let name = path.next().unwrap();
@@ -268,6 +348,83 @@ pub(crate) fn tree_remove_reg(tree: &mut IndexMap<String, RegisterTree>, path: &
     panic!("register not found");
 }
 
+pub(crate) fn tree_select_regs<'a>(
+    tree: &'a mut IndexMap<String, RegisterTree>,
+    pattern: &[&str],
+    registers: &mut Vec<&'a mut Register>,
+) {
+    let Some((name, tail)) = pattern.split_first() else { return };
+    for node in tree.values_mut() {
+        match node {
+            RegisterTree::Register(register) => {
+                if tail.is_empty() && glob_match(name, &register.name) {
+                    registers.push(register);
+                }
+            }
+            RegisterTree::Cluster(cluster) => {
+                if !tail.is_empty() && glob_match(name, &cluster.name) {
+                    tree_select_regs(&mut cluster.register, tail, registers);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn tree_remove_regs(
+    tree: &mut IndexMap<String, RegisterTree>,
+    pattern: &[&str],
+) -> usize {
+    let Some((name, tail)) = pattern.split_first() else { return 0 };
+    let mut count = 0;
+    tree.retain(|_, node| match node {
+        RegisterTree::Register(register) => {
+            let matched = tail.is_empty() && glob_match(name, &register.name);
+            count += usize::from(matched);
+            !matched
+        }
+        RegisterTree::Cluster(cluster) => {
+            if !tail.is_empty() && glob_match(name, &cluster.name) {
+                count += tree_remove_regs(&mut cluster.register, tail);
+            }
+            true
+        }
+    });
+    count
+}
+
+/// Re-keys the tree after its registers or clusters have been renamed, and
+/// updates alternate references to the renamed elements.
+pub(crate) fn tree_rekey(tree: &mut IndexMap<String, RegisterTree>) -> Result<()> {
+    let mut renamed = HashMap::new();
+    let mut rekeyed = IndexMap::with_capacity(tree.len());
+    for (key, mut node) in mem::take(tree) {
+        let name = match &mut node {
+            RegisterTree::Register(register) => register.name.clone(),
+            RegisterTree::Cluster(cluster) => {
+                tree_rekey(&mut cluster.register)?;
+                cluster.name.clone()
+            }
+        };
+        if name != key {
+            renamed.insert(key, name.clone());
+        }
+        if rekeyed.insert(name.clone(), node).is_some() {
+            bail!("register `{name}` already exists");
+        }
+    }
+    for node in rekeyed.values_mut() {
+        let alternate = match node {
+            RegisterTree::Register(register) => &mut register.alternate_register,
+            RegisterTree::Cluster(cluster) => &mut cluster.alternate_cluster,
+        };
+        if let Some(name) = alternate.as_ref().and_then(|name| renamed.get(name)) {
+            *alternate = Some(name.clone());
+        }
+    }
+    *tree = rekeyed;
+    Ok(())
+}
+
 impl FieldsWrapper {
     fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Field>, D::Error>
     where
@@ -285,7 +442,7 @@ where
 {
     let mut map = IndexMap::new();
     for register in Vec::<Register>::deserialize(deserializer)? {
-        map.insert(register.name.clone(), RegisterTree::Register(register));
+        tree_insert(&mut map, RegisterTree::Register(register));
     }
     Ok(map)
 }
@@ -0,0 +1,360 @@
+use crate::{Access, ResolvedDevice, ResolvedField, ResolvedPeripheral, ResolvedRegister};
+use indexmap::IndexMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+
+/// Semantic difference between two devices.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceDiff {
+    /// The list of changes.
+    pub changes: Vec<Change>,
+}
+
+/// A single change between two devices.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// The kind of the changed element.
+    pub element: Element,
+    /// The path of the element in the new device, or in the old device if the
+    /// element was removed.
+    pub path: String,
+    /// The kind of the change.
+    pub kind: ChangeKind,
+}
+
+/// Kind of a changed element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Element {
+    /// A peripheral.
+    Peripheral,
+    /// A register.
+    Register,
+    /// A field.
+    Field,
+}
+
+/// Kind of a change.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The element was added.
+    Added,
+    /// The element was removed.
+    Removed,
+    /// The element was renamed from the path `from`.
+    Renamed {
+        /// The path of the element in the old device.
+        from: String,
+    },
+    /// The address of the element changed.
+    Address {
+        /// The old address.
+        old: u32,
+        /// The new address.
+        new: u32,
+    },
+    /// The bit-width of the register changed.
+    Size {
+        /// The old bit-width.
+        old: u32,
+        /// The new bit-width.
+        new: u32,
+    },
+    /// The value of the register at RESET changed.
+    ResetValue {
+        /// The old value.
+        old: u32,
+        /// The new value.
+        new: u32,
+    },
+    /// The access rights of the element changed.
+    Access {
+        /// The old access rights.
+        old: Access,
+        /// The new access rights.
+        new: Access,
+    },
+    /// The bit range of the field changed.
+    BitRange {
+        /// The old bit range.
+        old: RangeInclusive<u32>,
+        /// The new bit range.
+        new: RangeInclusive<u32>,
+    },
+}
+
+impl ResolvedDevice {
+    /// Compares the device with a `new` revision of it.
+    ///
+    /// Elements are matched by name, ignoring their order. A removed element
+    /// and an added element at the same location are reported as a rename.
+    pub fn diff(&self, new: &ResolvedDevice) -> DeviceDiff {
+        let mut diff = DeviceDiff::default();
+        let old_peripherals =
+            self.peripherals.iter().map(|p| (p.name.clone(), p)).collect::<IndexMap<_, _>>();
+        let new_peripherals =
+            new.peripherals.iter().map(|p| (p.name.clone(), p)).collect::<IndexMap<_, _>>();
+        for pair in match_elements(&old_peripherals, &new_peripherals, |old, new| {
+            old.base_address == new.base_address
+        }) {
+            match pair {
+                Pair::Both(old, new) => diff.peripheral(old, new),
+                Pair::Removed(old) => {
+                    diff.push(Element::Peripheral, &old.name, ChangeKind::Removed);
+                }
+                Pair::Added(new) => {
+                    diff.push(Element::Peripheral, &new.name, ChangeKind::Added);
+                }
+            }
+        }
+        diff
+    }
+}
+
+impl DeviceDiff {
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    fn push(&mut self, element: Element, path: &str, kind: ChangeKind) {
+        self.changes.push(Change { element, path: path.to_owned(), kind });
+    }
+
+    fn peripheral(&mut self, old: &ResolvedPeripheral, new: &ResolvedPeripheral) {
+        if old.name != new.name {
+            self.push(Element::Peripheral, &new.name, ChangeKind::Renamed {
+                from: old.name.clone(),
+            });
+        }
+        if old.base_address != new.base_address {
+            self.push(Element::Peripheral, &new.name, ChangeKind::Address {
+                old: old.base_address,
+                new: new.base_address,
+            });
+        }
+        let old_registers =
+            old.registers.iter().map(|r| (r.path.join("/"), r)).collect::<IndexMap<_, _>>();
+        let new_registers =
+            new.registers.iter().map(|r| (r.path.join("/"), r)).collect::<IndexMap<_, _>>();
+        for pair in match_elements(&old_registers, &new_registers, |old_register, new_register| {
+            old_register.address - old.base_address == new_register.address - new.base_address
+        }) {
+            match pair {
+                Pair::Both(old_register, new_register) => {
+                    self.register(old, new, old_register, new_register);
+                }
+                Pair::Removed(old) => {
+                    self.push(Element::Register, &old.full_path(), ChangeKind::Removed);
+                }
+                Pair::Added(new) => {
+                    self.push(Element::Register, &new.full_path(), ChangeKind::Added);
+                }
+            }
+        }
+    }
+
+    fn register(
+        &mut self,
+        old_peripheral: &ResolvedPeripheral,
+        new_peripheral: &ResolvedPeripheral,
+        old: &ResolvedRegister,
+        new: &ResolvedRegister,
+    ) {
+        let path = new.full_path();
+        if old.path != new.path {
+            self.push(Element::Register, &path, ChangeKind::Renamed { from: old.full_path() });
+        }
+        if old.address - old_peripheral.base_address != new.address - new_peripheral.base_address {
+            self.push(Element::Register, &path, ChangeKind::Address {
+                old: old.address,
+                new: new.address,
+            });
+        }
+        if old.size != new.size {
+            self.push(Element::Register, &path, ChangeKind::Size { old: old.size, new: new.size });
+        }
+        if old.reset_value != new.reset_value {
+            self.push(Element::Register, &path, ChangeKind::ResetValue {
+                old: old.reset_value,
+                new: new.reset_value,
+            });
+        }
+        if old.access != new.access {
+            self.push(Element::Register, &path, ChangeKind::Access {
+                old: old.access,
+                new: new.access,
+            });
+        }
+        let old_fields = old.fields.iter().map(|f| (f.name.clone(), f)).collect::<IndexMap<_, _>>();
+        let new_fields = new.fields.iter().map(|f| (f.name.clone(), f)).collect::<IndexMap<_, _>>();
+        for pair in match_elements(&old_fields, &new_fields, |old, new| {
+            (old.bit_offset, old.bit_width) == (new.bit_offset, new.bit_width)
+        }) {
+            match pair {
+                Pair::Both(old_field, new_field) => {
+                    self.field(&old.full_path(), &path, old_field, new_field);
+                }
+                Pair::Removed(old_field) => {
+                    let path = format!("{}/{}", old.full_path(), old_field.name);
+                    self.push(Element::Field, &path, ChangeKind::Removed);
+                }
+                Pair::Added(new_field) => {
+                    let path = format!("{path}/{}", new_field.name);
+                    self.push(Element::Field, &path, ChangeKind::Added);
+                }
+            }
+        }
+    }
+
+    fn field(
+        &mut self,
+        old_register_path: &str,
+        register_path: &str,
+        old: &ResolvedField,
+        new: &ResolvedField,
+    ) {
+        let path = format!("{register_path}/{}", new.name);
+        if old.name != new.name {
+            let from = format!("{old_register_path}/{}", old.name);
+            self.push(Element::Field, &path, ChangeKind::Renamed { from });
+        }
+        if (old.bit_offset, old.bit_width) != (new.bit_offset, new.bit_width) {
+            self.push(Element::Field, &path, ChangeKind::BitRange {
+                old: old.bit_range(),
+                new: new.bit_range(),
+            });
+        }
+        if old.access != new.access {
+            self.push(Element::Field, &path, ChangeKind::Access {
+                old: old.access,
+                new: new.access,
+            });
+        }
+    }
+}
+
+impl fmt::Display for DeviceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { element, path, kind } = self;
+        match kind {
+            ChangeKind::Added => write!(f, "+ {element} {path}"),
+            ChangeKind::Removed => write!(f, "- {element} {path}"),
+            ChangeKind::Renamed { from } => write!(f, "~ {element} {path}: renamed from {from}"),
+            ChangeKind::Address { old, new } => {
+                write!(f, "~ {element} {path}: address 0x{old:08X} -> 0x{new:08X}")
+            }
+            ChangeKind::Size { old, new } => write!(f, "~ {element} {path}: size {old} -> {new}"),
+            ChangeKind::ResetValue { old, new } => {
+                write!(f, "~ {element} {path}: reset value 0x{old:08X} -> 0x{new:08X}")
+            }
+            ChangeKind::Access { old, new } => {
+                write!(f, "~ {element} {path}: access {old:?} -> {new:?}")
+            }
+            ChangeKind::BitRange { old, new } => write!(
+                f,
+                "~ {element} {path}: bit range [{}:{}] -> [{}:{}]",
+                old.end(),
+                old.start(),
+                new.end(),
+                new.start()
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Peripheral => write!(f, "peripheral"),
+            Self::Register => write!(f, "register"),
+            Self::Field => write!(f, "field"),
+        }
+    }
+}
+
+enum Pair<'a, T> {
+    Both(&'a T, &'a T),
+    Removed(&'a T),
+    Added(&'a T),
+}
+
+/// Pairs elements with equal names, then pairs the remaining elements for
+/// which `same_location` returns `true`.
+fn match_elements<'a, T>(
+    old: &IndexMap<String, &'a T>,
+    new: &IndexMap<String, &'a T>,
+    same_location: impl Fn(&T, &T) -> bool,
+) -> Vec<Pair<'a, T>> {
+    let mut added = new.keys().filter(|name| !old.contains_key(*name)).collect::<Vec<_>>();
+    let mut pairs = Vec::new();
+    for (name, &old_element) in old {
+        if let Some(&new_element) = new.get(name) {
+            pairs.push(Pair::Both(old_element, new_element));
+        } else if let Some(i) = added.iter().position(|name| same_location(old_element, new[*name]))
+        {
+            pairs.push(Pair::Both(old_element, new[added.remove(i)]));
+        } else {
+            pairs.push(Pair::Removed(old_element));
+        }
+    }
+    pairs.extend(added.into_iter().map(|name| Pair::Added(new[name])));
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, ResolvedDevice};
+
+    fn device(registers: &str) -> ResolvedDevice {
+        let device: Device = quick_xml::de::from_str(&format!(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue><peripherals>
+                <peripheral><name>P</name><baseAddress>0x1000</baseAddress>
+                    <registers>{registers}</registers>
+                </peripheral>
+            </peripherals></device>"
+        ))
+        .unwrap();
+        device.resolve().unwrap()
+    }
+
+    #[test]
+    fn diff_output() {
+        let old = device(
+            r"<register><name>CR</name><addressOffset>0</addressOffset><fields>
+                <field><name>EN</name><bitRange>[0:0]</bitRange></field>
+                <field><name>MODE</name><bitRange>[2:1]</bitRange></field>
+            </fields></register>
+            <register><name>SR</name><addressOffset>4</addressOffset></register>
+            <register><name>DR</name><addressOffset>8</addressOffset></register>",
+        );
+        let new = device(
+            r"<register><name>CTRL</name><addressOffset>0</addressOffset><fields>
+                <field><name>EN</name><bitRange>[0:0]</bitRange><access>read-only</access></field>
+                <field><name>MODE</name><bitRange>[3:1]</bitRange></field>
+            </fields></register>
+            <register><name>DR</name><addressOffset>0xC</addressOffset>
+                <resetValue>0xFF</resetValue></register>
+            <register><name>ISR</name><addressOffset>0x10</addressOffset><size>16</size></register>",
+        );
+        assert!(old.diff(&old).is_empty());
+        assert_eq!(
+            old.diff(&new).to_string(),
+            "~ register P/CTRL: renamed from P/CR\n~ field P/CTRL/EN: access ReadWrite -> \
+             ReadOnly\n~ field P/CTRL/MODE: bit range [2:1] -> [3:1]\n- register P/SR\n~ register \
+             P/DR: address 0x00001008 -> 0x0000100C\n~ register P/DR: reset value 0x00000000 -> \
+             0x000000FF\n+ register P/ISR\n"
+        );
+    }
+}
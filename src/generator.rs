@@ -1,27 +1,166 @@
-use crate::traverse::{for_each_clusters_combination, traverse_peripheral_registers};
+use crate::glob::glob_match;
+use crate::naming::{sanitize, Naming};
+use crate::secure::add_secure_aliases;
+use crate::traverse::{dim_name, for_each_clusters_combination, traverse_peripheral_registers};
 use crate::variant::{collect_variants, trace_variants};
-use crate::{Access, Device, Field, Peripheral, Register};
-use eyre::Result;
+use crate::{Access, Device, Field, Peripheral, Protection, Register};
+use eyre::{bail, Result};
 use indexmap::IndexMap;
 use std::collections::HashSet;
-use std::fs::File;
 use std::io::Write;
-use std::mem;
+use std::{mem, slice};
 
-pub trait RegisterTraitsCallback: Fn(String, Vec<String>, u32) -> Vec<String> {}
+/// Bounds shared by all callbacks. With the `rayon` feature the callbacks are
+/// called concurrently, so they must be thread-safe.
+#[cfg(feature = "rayon")]
+pub trait CallbackBounds: Send + Sync {}
 
-impl<T: Fn(String, Vec<String>, u32) -> Vec<String>> RegisterTraitsCallback for T {}
+#[cfg(feature = "rayon")]
+impl<T: Send + Sync> CallbackBounds for T {}
 
-pub trait CoreRegPredicate: Fn(String, Vec<String>) -> bool {}
+/// Bounds shared by all callbacks. With the `rayon` feature the callbacks are
+/// called concurrently, so they must be thread-safe.
+#[cfg(not(feature = "rayon"))]
+pub trait CallbackBounds {}
 
-impl<T: Fn(String, Vec<String>) -> bool> CoreRegPredicate for T {}
+#[cfg(not(feature = "rayon"))]
+impl<T> CallbackBounds for T {}
+
+pub trait RegisterTraitsCallback:
+    Fn(String, Vec<String>, u32) -> Vec<String> + CallbackBounds
+{
+}
+
+impl<T: Fn(String, Vec<String>, u32) -> Vec<String> + CallbackBounds> RegisterTraitsCallback for T {}
+
+pub trait RegisterContextTraitsCallback:
+    Fn(&RegisterContext<'_>) -> Vec<String> + CallbackBounds
+{
+}
+
+impl<T: Fn(&RegisterContext<'_>) -> Vec<String> + CallbackBounds> RegisterContextTraitsCallback
+    for T
+{
+}
+
+pub trait FieldTraitsCallback: Fn(&FieldContext<'_>) -> Vec<String> + CallbackBounds {}
+
+impl<T: Fn(&FieldContext<'_>) -> Vec<String> + CallbackBounds> FieldTraitsCallback for T {}
+
+pub trait SecureAliasCallback: Fn(String, u32) -> Option<u32> + CallbackBounds {}
+
+impl<T: Fn(String, u32) -> Option<u32> + CallbackBounds> SecureAliasCallback for T {}
+
+pub trait CoreRegPredicate: Fn(String, Vec<String>) -> bool + CallbackBounds {}
+
+impl<T: Fn(String, Vec<String>) -> bool + CallbackBounds> CoreRegPredicate for T {}
+
+/// Resolved properties of a register instance passed to
+/// [`Generator::register_traits_context_callback`].
+#[non_exhaustive]
+pub struct RegisterContext<'a> {
+    /// The peripheral instance name.
+    pub peripheral_name: &'a str,
+    /// Cluster instance names followed by the register instance name.
+    pub name: &'a [String],
+    /// The absolute address of the register.
+    pub address: u32,
+    /// The bit-width of the register.
+    pub size: u32,
+    /// The value of the register at RESET.
+    pub reset_value: u32,
+    /// The access rights for the register, if specified.
+    pub access: Option<Access>,
+    /// The protection level for the register, if specified.
+    pub protection: Option<Protection>,
+    /// Descriptions of the containing clusters followed by the description of
+    /// the register.
+    pub description: &'a [String],
+    /// The register from the description.
+    pub register: &'a Register,
+    /// Paths of the other register instances at the same memory location in
+    /// the `PERIPHERAL/CLUSTER/REGISTER` form.
+    pub variants: &'a [String],
+}
+
+/// Resolved properties of a field instance passed to
+/// [`Generator::field_traits_callback`].
+#[non_exhaustive]
+pub struct FieldContext<'a> {
+    /// The peripheral instance name.
+    pub peripheral_name: &'a str,
+    /// Cluster instance names followed by the register instance name.
+    pub register_name: &'a [String],
+    /// The field instance name.
+    pub name: &'a str,
+    /// The bit offset of the field instance within the register.
+    pub offset: u32,
+    /// The bit-width of the field.
+    pub width: u32,
+    /// The access rights for the field, inherited from the register if not
+    /// specified.
+    pub access: Option<Access>,
+    /// The bit-band alias address of a single-bit field, if bit-band
+    /// generation is enabled and the register lies in a bit-band region.
+    pub bit_band_alias: Option<u32>,
+    /// The field from the description.
+    pub field: &'a Field,
+}
+
+/// Strategy of splitting register bindings into shards.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Sharding {
+    /// Distributes register instances of each peripheral over the shards in
+    /// turn. Adding a register reshuffles the rest of the peripheral.
+    #[default]
+    Stagger,
+    /// Keeps all registers of a peripheral instance in one shard chosen by a
+    /// stable hash of the peripheral name.
+    Peripheral,
+    /// Chooses the shard by a stable hash of the register token name.
+    Register,
+}
+
+/// Register instances sharing a memory location, assigned to a shard, along
+/// with their traits.
+type ShardVariants<'a> = (usize, Vec<(&'a Register, Instance)>, Vec<Traits>);
+
+/// Traits of a register instance and of its generated field instances.
+pub(crate) struct Traits {
+    pub(crate) register: Vec<String>,
+    pub(crate) fields: IndexMap<String, Vec<String>>,
+}
+
+/// Device description prepared for generation by [`Generator::prepare`].
+#[derive(Clone, Debug)]
+pub struct PreparedDevice {
+    device: Device,
+    exclude_peripherals: Vec<String>,
+    secure_prefix: Option<String>,
+    renames: Vec<(String, String)>,
+    filters: [Vec<String>; 4],
+}
 
 /// Memory-mapped register bindings generator.
+#[allow(clippy::struct_excessive_bools)]
 pub struct Generator<'a> {
     macro_name: &'a str,
-    exclude_peripherals: Vec<&'a str>,
-    register_traits_callback: Option<Box<dyn RegisterTraitsCallback>>,
+    pub(crate) exclude_peripherals: Vec<&'a str>,
+    pub(crate) register_traits_callback: Option<Box<dyn RegisterContextTraitsCallback>>,
+    field_traits_callback: Option<Box<dyn FieldTraitsCallback>>,
     core_regs: Option<(&'a str, &'a str, Box<dyn CoreRegPredicate>)>,
+    cores: Vec<(&'a str, &'a str, &'a str, Vec<&'a str>)>,
+    secure_aliases: Option<(&'a str, Box<dyn SecureAliasCallback>)>,
+    pub(crate) periph_families: Vec<(&'a str, Vec<&'a str>)>,
+    group_peripherals: bool,
+    bit_band: bool,
+    protection_traits: bool,
+    sharding: Sharding,
+    pub(crate) filters: Filters<'a>,
+    pub(crate) naming: Naming<'a>,
+    #[cfg(feature = "rayon")]
+    parallel: bool,
 }
 
 impl<'a> Generator<'a> {
@@ -31,7 +170,19 @@ impl<'a> Generator<'a> {
             macro_name,
             exclude_peripherals: Vec::new(),
             register_traits_callback: None,
+            field_traits_callback: None,
             core_regs: None,
+            cores: Vec::new(),
+            secure_aliases: None,
+            periph_families: Vec::new(),
+            group_peripherals: false,
+            bit_band: false,
+            protection_traits: false,
+            sharding: Sharding::default(),
+            filters: Filters::default(),
+            naming: Naming::default(),
+            #[cfg(feature = "rayon")]
+            parallel: false,
         }
     }
 
@@ -46,110 +197,587 @@ impl<'a> Generator<'a> {
         self
     }
 
+    /// Extracts peripherals private to the core `name` of a multi-core device
+    /// into a separate set of tokens.
+    ///
+    /// `peripherals` is a list of glob patterns. Matching peripherals are
+    /// omitted from the shared index, so each core can have its own peripheral
+    /// at the same address. Registers selected by [`Generator::core_regs`] are
+    /// excluded from the core index in the same way as from the shared index.
+    ///
+    /// Cores are configured explicitly: the `<cpu>` element of the SVD file is
+    /// not used, and only the register index is split per core.
+    pub fn core(
+        &mut self,
+        name: &'a str,
+        macro_name: &'a str,
+        prev_macro: &'a str,
+        peripherals: &[&'a str],
+    ) -> &mut Self {
+        self.cores.push((name, macro_name, prev_macro, peripherals.to_vec()));
+        self
+    }
+
+    /// Defines a family of peripherals sharing one generic peripheral in
+    /// [`Generator::generate_periph_maps`].
+    ///
+    /// `peripherals` is a list of glob patterns matching peripheral instances.
+    pub fn periph_family(&mut self, name: &'a str, peripherals: &[&'a str]) -> &mut Self {
+        self.periph_families.push((name, peripherals.to_vec()));
+        self
+    }
+
+    /// Generates secure aliases of peripherals for Armv8-M devices with the
+    /// security extension.
+    ///
+    /// Described peripherals are treated as non-secure. For each peripheral
+    /// for which `secure_alias_callback` returns the secure base address, an
+    /// alias named with `prefix` is added. Registers protected as secure are
+    /// generated only for the alias, and registers protected as non-secure
+    /// only for the original peripheral.
+    pub fn secure_aliases(
+        &mut self,
+        prefix: &'a str,
+        secure_alias_callback: impl SecureAliasCallback + 'static,
+    ) -> &mut Self {
+        self.secure_aliases = Some((prefix, Box::new(secure_alias_callback)));
+        self
+    }
+
+    /// Nests peripherals of the same `groupName` into a module named after the
+    /// group in the generated index.
+    ///
+    /// Generating the index fails if a group module has the same name as an
+    /// ungrouped peripheral module.
+    pub fn group_peripherals(&mut self) -> &mut Self {
+        self.group_peripherals = true;
+        self
+    }
+
+    /// Marks registers inside the Cortex-M SRAM and peripheral bit-band regions
+    /// with `RegBitBand`, and documents bit-band alias addresses of their
+    /// single-bit fields.
+    pub fn bit_band(&mut self) -> &mut Self {
+        self.bit_band = true;
+        self
+    }
+
+    /// Marks registers protected as secure with `SecureReg`, and registers
+    /// protected as privileged with `PrivilegedReg`.
+    pub fn protection_traits(&mut self) -> &mut Self {
+        self.protection_traits = true;
+        self
+    }
+
+    /// Sets the strategy of splitting register bindings into shards.
+    pub fn sharding(&mut self, sharding: Sharding) -> &mut Self {
+        self.sharding = sharding;
+        self
+    }
+
+    /// Renders register bindings concurrently. The output is identical to the
+    /// serial generation.
+    ///
+    /// Register instances are collected per peripheral, and their traits and
+    /// text are produced concurrently. Instance deduplication, filtering, and
+    /// shard assignment are then applied in the peripheral order, and the
+    /// rendered variants are written in that order. With the `rayon` feature
+    /// all callbacks must be `Send + Sync`.
+    #[cfg(feature = "rayon")]
+    pub fn parallel(&mut self) -> &mut Self {
+        self.parallel = true;
+        self
+    }
+
     /// Extends the list of peripherals to exclude from generated bindings.
     pub fn exclude_peripherals(&mut self, exclude_peripherals: &[&'a str]) -> &mut Self {
         self.exclude_peripherals.extend(exclude_peripherals);
         self
     }
 
+    /// Extends the list of glob patterns of registers to generate. If the list
+    /// is empty, all registers are generated.
+    ///
+    /// Patterns are matched against the `PERIPHERAL/CLUSTER/REGISTER` paths of
+    /// register instances, and `*` also matches `/`.
+    pub fn include_registers(&mut self, patterns: &[&'a str]) -> &mut Self {
+        self.filters.include_registers.extend(patterns);
+        self
+    }
+
+    /// Extends the list of glob patterns of registers to skip.
+    ///
+    /// Patterns are matched against the `PERIPHERAL/CLUSTER/REGISTER` paths of
+    /// register instances, and `*` also matches `/`.
+    pub fn exclude_registers(&mut self, patterns: &[&'a str]) -> &mut Self {
+        self.filters.exclude_registers.extend(patterns);
+        self
+    }
+
+    /// Extends the list of glob patterns of fields to generate. If the list is
+    /// empty, all fields are generated.
+    ///
+    /// Patterns are matched against the `PERIPHERAL/CLUSTER/REGISTER/FIELD`
+    /// paths of field instances, and `*` also matches `/`.
+    pub fn include_fields(&mut self, patterns: &[&'a str]) -> &mut Self {
+        self.filters.include_fields.extend(patterns);
+        self
+    }
+
+    /// Extends the list of glob patterns of fields to skip.
+    ///
+    /// Patterns are matched against the `PERIPHERAL/CLUSTER/REGISTER/FIELD`
+    /// paths of field instances, and `*` also matches `/`.
+    pub fn exclude_fields(&mut self, patterns: &[&'a str]) -> &mut Self {
+        self.filters.exclude_fields.extend(patterns);
+        self
+    }
+
+    /// Renames the peripheral instance `from` to `to` in the generated
+    /// bindings.
+    pub fn rename_peripheral(&mut self, from: &'a str, to: &'a str) -> &mut Self {
+        self.naming.peripherals.insert(from, to);
+        self
+    }
+
+    /// Renames the register instance at the path `from` in the
+    /// `PERIPHERAL/CLUSTER/REGISTER` form to `to` in the generated bindings.
+    /// The new name replaces both cluster and register names.
+    pub fn rename_register(&mut self, from: &'a str, to: &'a str) -> &mut Self {
+        self.naming.registers.insert(from, to);
+        self
+    }
+
+    /// Renames the field instance at the path `from` in the
+    /// `PERIPHERAL/CLUSTER/REGISTER/FIELD` form to `to` in the generated
+    /// bindings.
+    pub fn rename_field(&mut self, from: &'a str, to: &'a str) -> &mut Self {
+        self.naming.fields.insert(from, to);
+        self
+    }
+
     /// Sets a callback function to provide additional register traits based on
     /// its memory address.
     pub fn register_traits_callback(
         &mut self,
         register_traits_callback: impl RegisterTraitsCallback + 'static,
+    ) -> &mut Self {
+        self.register_traits_callback = Some(Box::new(move |context: &RegisterContext<'_>| {
+            register_traits_callback(
+                context.peripheral_name.to_owned(),
+                context.name.to_vec(),
+                context.address,
+            )
+        }));
+        self
+    }
+
+    /// Sets a callback function to provide additional register traits based on
+    /// all resolved properties of the register. Replaces the callback set by
+    /// [`Generator::register_traits_callback`].
+    pub fn register_traits_context_callback(
+        &mut self,
+        register_traits_callback: impl RegisterContextTraitsCallback + 'static,
     ) -> &mut Self {
         self.register_traits_callback = Some(Box::new(register_traits_callback));
         self
     }
 
-    /// Generates register bindings.
+    /// Sets a callback function to provide additional field traits based on
+    /// the resolved properties of the field.
+    pub fn field_traits_callback(
+        &mut self,
+        field_traits_callback: impl FieldTraitsCallback + 'static,
+    ) -> &mut Self {
+        self.field_traits_callback = Some(Box::new(field_traits_callback));
+        self
+    }
+
+    /// Normalizes the device and traces register variants once for multiple
+    /// generation calls.
+    ///
+    /// The result depends on the list of excluded peripherals and secure
+    /// aliases, so they must be configured before the call. Generated names
+    /// are checked for clashes here as well, so renames and filters must also
+    /// be configured before the call. Generation fails if any of them differ
+    /// from the generator's configuration.
+    pub fn prepare(&self, mut device: Device) -> Result<PreparedDevice> {
+        normalize(&mut device);
+        if let Some((prefix, secure_alias_callback)) = &self.secure_aliases {
+            add_secure_aliases(
+                &mut device,
+                prefix,
+                secure_alias_callback.as_ref(),
+                &self.exclude_peripherals,
+            )?;
+        }
+        trace_variants(&mut device, &self.exclude_peripherals)?;
+        self.check_names(&device)?;
+        let exclude_peripherals =
+            self.exclude_peripherals.iter().map(|&name| name.to_owned()).collect();
+        let secure_prefix = self.secure_aliases.as_ref().map(|&(prefix, _)| prefix.to_owned());
+        let renames = self.naming.renames();
+        let filters = self.filters.patterns();
+        Ok(PreparedDevice { device, exclude_peripherals, secure_prefix, renames, filters })
+    }
+
+    pub(crate) fn prepared<'d>(&self, device: &'d PreparedDevice) -> Result<&'d Device> {
+        if !device.exclude_peripherals.iter().eq(&self.exclude_peripherals) {
+            bail!("device was prepared with a different list of excluded peripherals");
+        }
+        if device.secure_prefix.as_deref()
+            != self.secure_aliases.as_ref().map(|&(prefix, _)| prefix)
+        {
+            bail!("device was prepared with different secure aliases");
+        }
+        if device.renames != self.naming.renames() || device.filters != self.filters.patterns() {
+            bail!("device was prepared with different renames or filters");
+        }
+        Ok(&device.device)
+    }
+
+    /// Generates register bindings of the shard number `pool_number` out of
+    /// `pool_size` shards.
+    ///
+    /// Shards are numbered from 1, so `pool_number` must be in the range
+    /// `1..=pool_size`.
     pub fn generate_regs(
-        self,
-        output: &mut File,
-        mut device: Device,
+        &self,
+        output: &mut impl Write,
+        device: &PreparedDevice,
         pool_number: usize,
         pool_size: usize,
     ) -> Result<()> {
-        normalize(&mut device);
-        trace_variants(&mut device, &self.exclude_peripherals)?;
-        let mut counter = 0;
-        let stagger = move || {
-            counter += 1;
-            counter % pool_size != pool_number - 1
-        };
+        if pool_number == 0 || pool_number > pool_size {
+            bail!("shard number {pool_number} is out of range 1..={pool_size}");
+        }
+        let shards = self
+            .collect_shards(device, pool_size)?
+            .into_iter()
+            .filter(|(shard, _, _)| *shard == pool_number - 1)
+            .map(|(_, instances, traits)| (0, instances, traits))
+            .collect::<Vec<_>>();
+        self.write_shards(slice::from_mut(output), &shards)
+    }
+
+    /// Generates register bindings split into `outputs.len()` shards in one
+    /// pass.
+    pub fn generate_shards(
+        &self,
+        outputs: &mut [impl Write],
+        device: &PreparedDevice,
+    ) -> Result<()> {
+        let shards = self.collect_shards(device, outputs.len())?;
+        self.write_shards(outputs, &shards)
+    }
+
+    fn collect_shards<'d>(
+        &self,
+        device: &'d PreparedDevice,
+        pool_size: usize,
+    ) -> Result<Vec<ShardVariants<'d>>> {
+        if pool_size == 0 {
+            bail!("number of shards must be positive");
+        }
+        let device = self.prepared(device)?;
+        let peripherals = device
+            .peripherals
+            .values()
+            .filter(|peripheral| {
+                !self.exclude_peripherals.iter().any(|&name| name == peripheral.name)
+            })
+            .collect::<Vec<_>>();
+        let groups = self.map(peripherals, |peripheral| {
+            let mut groups = Vec::new();
+            peripheral_instances(device, peripheral, |instances| {
+                groups.push(instances);
+                Ok(())
+            })?;
+            Ok(groups)
+        })?;
+        let mut variants = Vec::new();
         let mut generated = HashSet::new();
-        for peripheral in device.peripherals.values() {
-            if self.exclude_peripherals.iter().any(|&name| name == peripheral.name) {
-                continue;
+        for groups in groups {
+            let mut counter = 0;
+            for mut instances in groups {
+                if !first_generation(&mut generated, &instances) {
+                    continue;
+                }
+                counter += 1;
+                if !instances.is_empty() {
+                    instances.retain(|(_, instance)| {
+                        self.filters.register(&instance.peripheral_name, &instance.name)
+                    });
+                    if instances.is_empty() {
+                        continue;
+                    }
+                }
+                let shard = match (self.sharding, instances.first()) {
+                    (Sharding::Stagger, _) => counter % pool_size,
+                    (Sharding::Peripheral, Some((_, instance))) => {
+                        hash_shard(&instance.peripheral_name, pool_size)
+                    }
+                    (Sharding::Register, Some((_, instance))) => hash_shard(
+                        &format!("{}_{}", instance.peripheral_name, instance.name.join("_")),
+                        pool_size,
+                    ),
+                    (_, None) => continue,
+                };
+                variants.push((shard, instances));
             }
-            generate_peripheral(
-                output,
-                &device,
-                peripheral,
-                &mut generated,
-                stagger,
-                &self.register_traits_callback,
+        }
+        self.map(variants, |(shard, instances)| {
+            let traits = self.instance_traits(&instances);
+            Ok((shard, instances, traits))
+        })
+    }
+
+    /// Maps `items` in order, concurrently if [`Generator::parallel`] is set.
+    #[cfg(feature = "rayon")]
+    fn map<T: Send, U: Send>(
+        &self,
+        items: Vec<T>,
+        f: impl Fn(T) -> Result<U> + Send + Sync,
+    ) -> Result<Vec<U>> {
+        use rayon::prelude::*;
+        if self.parallel {
+            items.into_par_iter().map(f).collect()
+        } else {
+            items.into_iter().map(f).collect()
+        }
+    }
+
+    /// Maps `items` in order.
+    #[cfg(not(feature = "rayon"))]
+    #[allow(clippy::unused_self)]
+    fn map<T, U>(&self, items: Vec<T>, f: impl Fn(T) -> Result<U>) -> Result<Vec<U>> {
+        items.into_iter().map(f).collect()
+    }
+
+    pub(crate) fn instance_traits(&self, instances: &[(&Register, Instance)]) -> Vec<Traits> {
+        register_traits(
+            instances,
+            self.bit_band,
+            self.protection_traits,
+            self.register_traits_callback.as_deref(),
+        )
+        .into_iter()
+        .zip(instances)
+        .map(|(register_traits, (register, instance))| {
+            let mut fields = IndexMap::new();
+            for field in &register.fields {
+                for number in 0..field.dim.unwrap_or(1) {
+                    let name = dim_name(number, &field.name);
+                    if !self.filters.field(&instance.peripheral_name, &instance.name, &name) {
+                        continue;
+                    }
+                    let access = field.access.or(instance.access);
+                    let offset = field.bit_offset() + number * field.dim_increment.unwrap_or(0);
+                    let mut traits = field_traits(field, access)
+                        .into_iter()
+                        .map(ToOwned::to_owned)
+                        .collect::<Vec<_>>();
+                    if let Some(field_traits_callback) = &self.field_traits_callback {
+                        traits.extend(field_traits_callback(&FieldContext {
+                            peripheral_name: &instance.peripheral_name,
+                            register_name: &instance.name,
+                            name: &name,
+                            offset,
+                            width: field.bit_width(),
+                            access,
+                            bit_band_alias: self
+                                .bit_band
+                                .then(|| bit_band_alias(instance, offset, field.bit_width()))
+                                .flatten(),
+                            field,
+                        }));
+                    }
+                    fields.insert(name, traits);
+                }
+            }
+            Traits { register: register_traits, fields }
+        })
+        .collect()
+    }
+
+    #[cfg_attr(not(feature = "rayon"), allow(clippy::unused_self))]
+    fn write_shards(&self, outputs: &mut [impl Write], shards: &[ShardVariants<'_>]) -> Result<()> {
+        #[cfg(feature = "rayon")]
+        if self.parallel {
+            use rayon::prelude::*;
+            let (bit_band, naming) = (self.bit_band, &self.naming);
+            let rendered = shards
+                .par_iter()
+                .map(|(shard, instances, traits)| {
+                    let mut buffer = Vec::new();
+                    generate_variants(&mut buffer, instances, traits, bit_band, naming)?;
+                    Ok((*shard, buffer))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            for (shard, buffer) in rendered {
+                outputs[shard].write_all(&buffer)?;
+            }
+            return Ok(());
+        }
+        for (shard, instances, traits) in shards {
+            generate_variants(
+                &mut outputs[*shard],
+                instances,
+                traits,
+                self.bit_band,
+                &self.naming,
             )?;
         }
         Ok(())
     }
 
     /// Generates registers index.
-    pub fn generate_index(self, index_output: &mut File, mut device: Device) -> Result<()> {
-        normalize(&mut device);
-        trace_variants(&mut device, &self.exclude_peripherals)?;
-        let output: &mut File = index_output;
+    pub fn generate_index(&self, output: &mut impl Write, device: &PreparedDevice) -> Result<()> {
+        let device = self.prepared(device)?;
         let mut index = IndexMap::new();
         for peripheral in device.peripherals.values() {
             if self.exclude_peripherals.iter().any(|&name| name == peripheral.name) {
                 continue;
             }
-            generate_peripheral_index(&device, peripheral, &mut index)?;
+            generate_peripheral_index(device, peripheral, &self.filters, &mut index)?;
         }
+        let is_private = |patterns: &[&str], name: &str| {
+            patterns.iter().any(|pattern| glob_match(pattern, name))
+        };
+        let core_index = |patterns: &[&str]| {
+            index
+                .iter()
+                .filter(|(name, _)| is_private(patterns, name))
+                .map(|(name, registers)| (name.clone(), registers.clone()))
+                .collect::<IndexMap<_, _>>()
+        };
+        let mut shared_index = index.clone();
+        shared_index.retain(|name, _| {
+            !self.cores.iter().any(|(_, _, _, peripherals)| is_private(peripherals, name))
+        });
         generate_reg_tokens(
             output,
-            &device,
-            &index,
+            device,
+            &shared_index,
             &format!("Defines an index of {} MCU-level register tokens.", device.name),
             self.macro_name,
             None,
             self.core_regs.as_ref().map(|(_, _, core_regs_predicate)| core_regs_predicate),
             false,
+            self.group_peripherals,
+            &self.naming,
         )?;
         if let Some((macro_name, prev_macro, core_regs_predicate)) = &self.core_regs {
             writeln!(output)?;
             generate_reg_tokens(
                 output,
-                &device,
-                &index,
+                device,
+                &shared_index,
                 &format!("Defines an index of {} core-level register tokens.", device.name),
                 macro_name,
                 Some(prev_macro),
                 Some(core_regs_predicate),
                 true,
+                self.group_peripherals,
+                &self.naming,
+            )?;
+        }
+        for (name, macro_name, prev_macro, peripherals) in &self.cores {
+            writeln!(output)?;
+            generate_reg_tokens(
+                output,
+                device,
+                &core_index(peripherals),
+                &format!(
+                    "Defines an index of {} {name} core-private register tokens.",
+                    device.name
+                ),
+                macro_name,
+                Some(prev_macro),
+                self.core_regs.as_ref().map(|(_, _, core_regs_predicate)| core_regs_predicate),
+                false,
+                self.group_peripherals,
+                &self.naming,
             )?;
         }
         Ok(())
     }
 }
 
-struct Instance {
-    description: Vec<String>,
-    peripheral_name: String,
-    name: Vec<String>,
-    address: u32,
-    size: u32,
-    reset_value: u32,
-    access: Option<Access>,
+pub(crate) struct Instance {
+    pub(crate) description: Vec<String>,
+    pub(crate) peripheral_name: String,
+    pub(crate) name: Vec<String>,
+    pub(crate) address: u32,
+    pub(crate) size: u32,
+    pub(crate) reset_value: u32,
+    pub(crate) access: Option<Access>,
+    pub(crate) protection: Option<Protection>,
 }
 
-fn generate_peripheral(
-    output: &mut File,
-    device: &Device,
-    peripheral: &Peripheral,
+#[derive(Default)]
+pub(crate) struct Filters<'a> {
+    include_registers: Vec<&'a str>,
+    exclude_registers: Vec<&'a str>,
+    include_fields: Vec<&'a str>,
+    exclude_fields: Vec<&'a str>,
+}
+
+impl Filters<'_> {
+    fn patterns(&self) -> [Vec<String>; 4] {
+        [
+            &self.include_registers,
+            &self.exclude_registers,
+            &self.include_fields,
+            &self.exclude_fields,
+        ]
+        .map(|patterns| patterns.iter().map(|&pattern| pattern.to_owned()).collect())
+    }
+
+    pub(crate) fn register(&self, peripheral_name: &str, name: &[String]) -> bool {
+        let path = format!("{peripheral_name}/{}", name.join("/"));
+        filter(&self.include_registers, &self.exclude_registers, &path)
+    }
+
+    pub(crate) fn field(&self, peripheral_name: &str, name: &[String], field_name: &str) -> bool {
+        let path = format!("{peripheral_name}/{}/{field_name}", name.join("/"));
+        filter(&self.include_fields, &self.exclude_fields, &path)
+    }
+}
+
+fn filter(include: &[&str], exclude: &[&str], path: &str) -> bool {
+    (include.is_empty() || include.iter().any(|pattern| glob_match(pattern, path)))
+        && !exclude.iter().any(|pattern| glob_match(pattern, path))
+}
+
+pub(crate) fn generate_peripheral<'a>(
+    device: &'a Device,
+    peripheral: &'a Peripheral,
     generated: &mut HashSet<(String, Vec<String>)>,
-    mut stagger: impl FnMut() -> bool,
-    register_traits_callback: &Option<Box<dyn RegisterTraitsCallback>>,
+    mut f: impl FnMut(Vec<(&'a Register, Instance)>) -> Result<()>,
+) -> Result<()> {
+    peripheral_instances(device, peripheral, |instances| {
+        if first_generation(generated, &instances) { f(instances) } else { Ok(()) }
+    })
+}
+
+/// Records the instances as generated. Returns `false` if any of them was
+/// already generated, in which case the whole group is skipped.
+fn first_generation(
+    generated: &mut HashSet<(String, Vec<String>)>,
+    instances: &[(&Register, Instance)],
+) -> bool {
+    instances.iter().all(|(_, instance)| {
+        generated.insert((instance.peripheral_name.clone(), instance.name.clone()))
+    })
+}
+
+/// Calls `f` with each group of register instances of the peripheral, without
+/// deduplication.
+fn peripheral_instances<'a>(
+    device: &'a Device,
+    peripheral: &'a Peripheral,
+    mut f: impl FnMut(Vec<(&'a Register, Instance)>) -> Result<()>,
 ) -> Result<()> {
     let parent = peripheral.derived_from(device)?;
     traverse_peripheral_registers(peripheral, parent, |clusters, register| {
@@ -168,6 +796,12 @@ fn generate_peripheral(
                     variant.register.size(device, variant.peripheral, parent)?,
                     variant.register.reset_value(device, variant.peripheral, parent)?,
                     variant.register.access(device, variant.peripheral, parent),
+                    variant.register.protection(
+                        device,
+                        variant.peripheral,
+                        parent,
+                        &variant.clusters,
+                    ),
                 ))
             })
             .collect::<Result<Vec<_>>>()?;
@@ -193,7 +827,7 @@ fn generate_peripheral(
                     Ok((name, address))
                 },
                 |clusters_data| {
-                    'outer: for register_n in
+                    for register_n in
                         0..variants.iter().map(|v| v.register.dim.unwrap_or(1)).max().unwrap_or(1)
                     {
                         let mut instances = Vec::new();
@@ -203,7 +837,8 @@ fn generate_peripheral(
                             {
                                 let (peripheral_name, peripheral_offset) = &peripheral_data[i];
                                 let (clusters_name, clusters_address) = &clusters_data[i];
-                                let (ref description, size, reset_value, access) = register_data[i];
+                                let (ref description, size, reset_value, access, protection) =
+                                    register_data[i];
                                 let mut name = clusters_name.clone();
                                 name.push(dim_name(register_n, &variant.register.name));
                                 let address = variant.peripheral.base_address
@@ -211,9 +846,6 @@ fn generate_peripheral(
                                     + clusters_address
                                     + variant.register.address_offset
                                     + register_n * variant.register.dim_increment.unwrap_or(0);
-                                if !generated.insert((peripheral_name.to_string(), name.clone())) {
-                                    continue 'outer;
-                                }
                                 instances.push((variant.register, Instance {
                                     description: description.clone(),
                                     peripheral_name: peripheral_name.clone(),
@@ -222,12 +854,11 @@ fn generate_peripheral(
                                     size,
                                     reset_value,
                                     access,
+                                    protection,
                                 }));
                             }
                         }
-                        if !stagger() {
-                            generate_variants(output, &instances, register_traits_callback)?;
-                        }
+                        f(instances)?;
                     }
                     Ok(())
                 },
@@ -241,6 +872,7 @@ fn generate_peripheral(
 fn generate_peripheral_index(
     device: &Device,
     peripheral: &Peripheral,
+    filters: &Filters<'_>,
     index: &mut IndexMap<String, IndexMap<Vec<String>, bool>>,
 ) -> Result<()> {
     let parent = peripheral.derived_from(device)?;
@@ -264,16 +896,21 @@ fn generate_peripheral_index(
                     for register_n in
                         0..variants.iter().map(|v| v.register.dim.unwrap_or(1)).max().unwrap_or(1)
                     {
+                        let mut primary = true;
                         for (i, variant) in variants.iter().enumerate() {
                             if peripheral_n < variant.peripheral.dim.unwrap_or(1)
                                 && register_n < variant.register.dim.unwrap_or(1)
                             {
                                 let mut name = clusters_name[i].clone();
                                 name.push(dim_name(register_n, &variant.register.name));
+                                if !filters.register(&peripheral_name[i], &name) {
+                                    continue;
+                                }
                                 let peripheral =
                                     index.entry(peripheral_name[i].clone()).or_default();
-                                if i == 0 {
+                                if primary {
                                     peripheral.entry(name).or_insert(true);
+                                    primary = false;
                                 } else {
                                     peripheral.insert(name, false);
                                 }
@@ -290,29 +927,29 @@ fn generate_peripheral_index(
 }
 
 fn generate_variants(
-    output: &mut File,
+    output: &mut impl Write,
     instances: &[(&Register, Instance)],
-    register_traits_callback: &Option<Box<dyn RegisterTraitsCallback>>,
+    traits: &[Traits],
+    bit_band: bool,
+    naming: &Naming<'_>,
 ) -> Result<()> {
     writeln!(output, "reg! {{")?;
-    for (register, instance) in instances {
-        let Instance { description, peripheral_name, name, address, size, reset_value, access } =
+    for ((register, instance), traits) in instances.iter().zip(traits) {
+        let Instance { description, peripheral_name, name, address, size, reset_value, .. } =
             instance;
         for description in description {
             for line in description.lines() {
                 writeln!(output, "    /// {}", line.trim())?;
             }
         }
-        write!(output, "    pub {} ", peripheral_name)?;
-        for (i, name) in name.iter().enumerate() {
-            if i > 0 {
-                write!(output, "_")?;
-            }
-            write!(output, "{}", name)?;
-        }
-        writeln!(output, " => {{")?;
+        writeln!(
+            output,
+            "    pub {} {} => {{",
+            naming.peripheral(peripheral_name),
+            naming.register(peripheral_name, name)
+        )?;
         writeln!(output, "        address => 0x{:04X}_{:04X};", address >> 16, address & 0xFFFF)?;
-        writeln!(output, "        size => {};", size)?;
+        writeln!(output, "        size => {size};")?;
         writeln!(
             output,
             "        reset => 0x{:04X}_{:04X};",
@@ -320,29 +957,13 @@ fn generate_variants(
             reset_value & 0xFFFF
         )?;
         write!(output, "        traits => {{")?;
-        match access {
-            Some(Access::WriteOnly) => {
-                write!(output, " WReg")?;
-                write!(output, " WoReg")?;
-            }
-            Some(Access::ReadOnly) => {
-                write!(output, " RReg")?;
-                write!(output, " RoReg")?;
-            }
-            Some(Access::ReadWrite | Access::ReadWriteonce) | None => {
-                write!(output, " RReg")?;
-                write!(output, " WReg")?;
-            }
-        }
-        if let Some(register_traits_callback) = register_traits_callback {
-            for name in register_traits_callback(peripheral_name.clone(), name.clone(), *address) {
-                write!(output, " {name}")?;
-            }
+        for name in &traits.register {
+            write!(output, " {name}")?;
         }
         writeln!(output, " }};")?;
         writeln!(output, "        fields => {{")?;
         for field in &register.fields {
-            generate_field(output, field, *access)?;
+            generate_field(output, instance, field, &traits.fields, bit_band, naming)?;
         }
         writeln!(output, "        }};")?;
         writeln!(output, "    }};")?;
@@ -351,32 +972,45 @@ fn generate_variants(
     Ok(())
 }
 
-fn generate_field(output: &mut File, field: &Field, base_access: Option<Access>) -> Result<()> {
+fn generate_field(
+    output: &mut impl Write,
+    instance: &Instance,
+    field: &Field,
+    traits: &IndexMap<String, Vec<String>>,
+    bit_band: bool,
+    naming: &Naming<'_>,
+) -> Result<()> {
     for number in 0..field.dim.unwrap_or(1) {
+        let name = dim_name(number, &field.name);
+        let Some(traits) = traits.get(&name) else {
+            continue;
+        };
         let offset = number * field.dim_increment.unwrap_or(0);
         for line in field.description.lines() {
             writeln!(output, "            /// {}", line.trim())?;
         }
-        writeln!(output, "            {} => {{", dim_name(number, &field.name))?;
+        if let Some(alias) = bit_band
+            .then(|| bit_band_alias(instance, field.bit_offset() + offset, field.bit_width()))
+            .flatten()
+        {
+            writeln!(output, "            ///")?;
+            writeln!(
+                output,
+                "            /// Bit-band alias: 0x{:04X}_{:04X}.",
+                alias >> 16,
+                alias & 0xFFFF
+            )?;
+        }
+        writeln!(
+            output,
+            "            {} => {{",
+            naming.field(&instance.peripheral_name, &instance.name, &name)
+        )?;
         writeln!(output, "                offset => {};", field.bit_offset() + offset)?;
         writeln!(output, "                width => {};", field.bit_width())?;
         write!(output, "                traits => {{")?;
-        match field.access.or(base_access) {
-            Some(Access::WriteOnly) => {
-                write!(output, " WWRegField")?;
-                write!(output, " WoWRegField")?;
-            }
-            Some(Access::ReadOnly) => {
-                write!(output, " RRRegField")?;
-                write!(output, " RoRRegField")?;
-            }
-            Some(Access::ReadWrite | Access::ReadWriteonce) | None => {
-                write!(output, " RRRegField")?;
-                write!(output, " WWRegField")?;
-            }
-        }
-        if field.force_bits {
-            write!(output, " ForceBits")?;
+        for name in traits {
+            write!(output, " {name}")?;
         }
         writeln!(output, " }};")?;
         writeln!(output, "            }};")?;
@@ -384,9 +1018,90 @@ fn generate_field(output: &mut File, field: &Field, base_access: Option<Access>)
     Ok(())
 }
 
+fn register_traits(
+    instances: &[(&Register, Instance)],
+    bit_band: bool,
+    protection_traits: bool,
+    register_traits_callback: Option<&dyn RegisterContextTraitsCallback>,
+) -> Vec<Vec<String>> {
+    let paths = instances
+        .iter()
+        .map(|(_, instance)| format!("{}/{}", instance.peripheral_name, instance.name.join("/")))
+        .collect::<Vec<_>>();
+    instances
+        .iter()
+        .enumerate()
+        .map(|(i, (register, instance))| {
+            let mut traits = match instance.access {
+                Some(Access::WriteOnly) => vec!["WReg".to_owned(), "WoReg".to_owned()],
+                Some(Access::ReadOnly) => vec!["RReg".to_owned(), "RoReg".to_owned()],
+                Some(Access::ReadWrite | Access::ReadWriteonce) | None => {
+                    vec!["RReg".to_owned(), "WReg".to_owned()]
+                }
+            };
+            match instance.protection.filter(|_| protection_traits) {
+                Some(Protection::Secure) => traits.push("SecureReg".to_owned()),
+                Some(Protection::Privileged) => traits.push("PrivilegedReg".to_owned()),
+                Some(Protection::NonSecure) | None => {}
+            }
+            if bit_band && bit_band_region(instance).is_some() {
+                traits.push("RegBitBand".to_owned());
+            }
+            if let Some(register_traits_callback) = register_traits_callback {
+                let mut variants = paths.clone();
+                variants.remove(i);
+                traits.extend(register_traits_callback(&RegisterContext {
+                    peripheral_name: &instance.peripheral_name,
+                    name: &instance.name,
+                    address: instance.address,
+                    size: instance.size,
+                    reset_value: instance.reset_value,
+                    access: instance.access,
+                    protection: instance.protection,
+                    description: &instance.description,
+                    register,
+                    variants: &variants,
+                }));
+            }
+            traits
+        })
+        .collect()
+}
+
+/// Returns the bit-band region and alias region base addresses for the
+/// register instance, if the register lies entirely in a bit-band region.
+fn bit_band_region(instance: &Instance) -> Option<(u32, u32)> {
+    const REGIONS: [(u32, u32); 2] = [(0x2000_0000, 0x2200_0000), (0x4000_0000, 0x4200_0000)];
+    const REGION_SIZE: u64 = 0x0010_0000;
+    let start = u64::from(instance.address);
+    let end = start + u64::from(instance.size / 8 + u32::from(instance.size % 8 != 0));
+    REGIONS
+        .into_iter()
+        .find(|&(base, _)| start >= u64::from(base) && end <= u64::from(base) + REGION_SIZE)
+}
+
+/// Returns the bit-band alias address of a single-bit field at the bit
+/// `offset` of the register instance.
+fn bit_band_alias(instance: &Instance, offset: u32, width: u32) -> Option<u32> {
+    let (base, alias_base) = bit_band_region(instance)?;
+    (width == 1).then(|| alias_base + (instance.address - base) * 32 + offset * 4)
+}
+
+fn field_traits(field: &Field, access: Option<Access>) -> Vec<&'static str> {
+    let mut traits = match access {
+        Some(Access::WriteOnly) => vec!["WWRegField", "WoWRegField"],
+        Some(Access::ReadOnly) => vec!["RRRegField", "RoRRegField"],
+        Some(Access::ReadWrite | Access::ReadWriteonce) | None => vec!["RRRegField", "WWRegField"],
+    };
+    if field.force_bits {
+        traits.push("ForceBits");
+    }
+    traits
+}
+
 #[allow(clippy::too_many_arguments, clippy::borrowed_box)]
 fn generate_reg_tokens(
-    output: &mut File,
+    output: &mut impl Write,
     device: &Device,
     index: &IndexMap<String, IndexMap<Vec<String>, bool>>,
     macro_doc: &str,
@@ -394,63 +1109,405 @@ fn generate_reg_tokens(
     prev_macro: Option<&str>,
     core_reg_predicate: Option<&Box<dyn CoreRegPredicate>>,
     core_regs: bool,
+    group_peripherals: bool,
+    naming: &Naming<'_>,
 ) -> Result<()> {
     writeln!(output, "reg::tokens! {{")?;
-    writeln!(output, "    /// {}", macro_doc)?;
-    writeln!(output, "    pub macro {};", macro_name)?;
+    writeln!(output, "    /// {macro_doc}")?;
+    writeln!(output, "    pub macro {macro_name};")?;
     if let Some(prev_macro) = prev_macro {
-        writeln!(output, "    use macro {};", prev_macro)?;
+        writeln!(output, "    use macro {prev_macro};")?;
     }
     writeln!(output, "    super::inner;")?;
     writeln!(output, "    crate::reg;")?;
+    let mut groups = IndexMap::<_, Vec<_>>::new();
     for (peripheral_name, registers) in index {
         let peripheral = &device.peripherals[peripheral_name];
         let parent = peripheral.derived_from(device)?;
-        if let Some(description) = peripheral.description(parent) {
-            for line in description.lines() {
-                writeln!(output, "    /// {}", line.trim())?;
-            }
+        let group_name = peripheral.group_name(parent).filter(|_| group_peripherals);
+        groups
+            .entry((group_name.is_some(), group_name.unwrap_or(peripheral_name)))
+            .or_default()
+            .push((peripheral_name, peripheral, parent, registers));
+    }
+    for (_, group_name) in groups.keys().filter(|(group, _)| *group) {
+        if let Some((_, peripheral_name)) = groups.keys().find(|(group, peripheral_name)| {
+            !group && naming.peripheral(peripheral_name) == sanitize(group_name)
+        }) {
+            bail!("peripheral group `{group_name}` collides with peripheral `{peripheral_name}`");
         }
-        writeln!(
-            output,
-            "    pub mod {}{} {{",
-            core_regs.then_some("!").unwrap_or_default(),
-            peripheral_name
-        )?;
-        for (name, primary) in registers {
-            let core_reg = core_reg_predicate.map_or(false, |predicate| {
-                let core_reg = !predicate(peripheral_name.clone(), name.clone());
-                if core_regs { core_reg } else { !core_reg }
-            });
-            write!(output, "        ")?;
-            if !primary || (!core_regs && core_reg) {
-                write!(output, "!")?;
+    }
+    for ((group, group_name), peripherals) in groups {
+        let indent = if group { "    " } else { "" };
+        if group {
+            writeln!(output, "    pub mod {} {{", sanitize(group_name))?;
+        }
+        for (peripheral_name, peripheral, parent, registers) in peripherals {
+            if let Some(description) = peripheral.description(parent) {
+                for line in description.lines() {
+                    writeln!(output, "{indent}    /// {}", line.trim())?;
+                }
             }
-            for (i, name) in name.iter().enumerate() {
-                if i > 0 {
-                    write!(output, "_")?;
+            writeln!(
+                output,
+                "{indent}    pub mod {}{} {{",
+                if core_regs { "!" } else { "" },
+                naming.peripheral(peripheral_name)
+            )?;
+            for (name, primary) in registers {
+                let core_reg = core_reg_predicate.map_or(false, |predicate| {
+                    let core_reg = !predicate(peripheral_name.clone(), name.clone());
+                    if core_regs { core_reg } else { !core_reg }
+                });
+                write!(output, "{indent}        ")?;
+                if !primary || (!core_regs && core_reg) {
+                    write!(output, "!")?;
                 }
-                write!(output, "{}", name)?;
+                writeln!(output, "{};", naming.register(peripheral_name, name))?;
             }
-            writeln!(output, ";")?;
+            writeln!(output, "{indent}    }}")?;
+        }
+        if group {
+            writeln!(output, "    }}")?;
         }
-        writeln!(output, "    }}")?;
     }
     writeln!(output, "}}")?;
     Ok(())
 }
 
-fn dim_name(number: u32, name: &str) -> String {
-    if let Some(name) = name.strip_suffix("[%s]") {
-        format!("{}_{}", name, number)
-    } else {
-        name.to_owned()
-    }
+/// Chooses a shard by the FNV-1a hash of `name`, which is stable across
+/// platforms and compiler versions.
+fn hash_shard(name: &str, pool_size: usize) -> usize {
+    let hash = name.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+    });
+    (hash % pool_size as u64) as usize
 }
 
-fn normalize(device: &mut Device) {
+pub(crate) fn normalize(device: &mut Device) {
     device.peripherals = mem::take(&mut device.peripherals)
         .into_iter()
         .map(|(_, peripheral)| (peripheral.name.clone(), peripheral))
         .collect();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{bit_band_alias, bit_band_region, Instance};
+    use crate::{Device, Generator, Sharding};
+
+    fn device(peripherals: &str) -> Device {
+        quick_xml::de::from_str(&format!(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue>
+                <peripherals>{peripherals}</peripherals>
+            </device>"
+        ))
+        .unwrap()
+    }
+
+    fn regs(generator: &Generator<'_>, device: Device) -> String {
+        let device = generator.prepare(device).unwrap();
+        let mut output = Vec::new();
+        generator.generate_regs(&mut output, &device, 1, 1).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn index(generator: &Generator<'_>, device: Device) -> String {
+        let device = generator.prepare(device).unwrap();
+        let mut output = Vec::new();
+        generator.generate_index(&mut output, &device).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    fn timer() -> Device {
+        device(
+            "<peripheral><name>TIM1</name><description>Timer</description>
+                <baseAddress>0x40000000</baseAddress>
+                <registers>
+                    <register><name>CR</name><description>Control</description>
+                        <addressOffset>0</addressOffset>
+                        <fields><field><name>EN</name><description>Enable</description>
+                            <bitOffset>0</bitOffset><bitWidth>1</bitWidth></field></fields>
+                    </register>
+                    <register><name>CR_ALT</name><description>Alternate control</description>
+                        <alternateRegister>CR</alternateRegister>
+                        <addressOffset>0</addressOffset></register>
+                    <register><name>SR</name><description>Status</description>
+                        <addressOffset>4</addressOffset></register>
+                </registers>
+            </peripheral>",
+        )
+    }
+
+    #[test]
+    fn filtered_primary_variant() {
+        let mut generator = Generator::new("tokens");
+        generator.exclude_registers(&["*/CR"]);
+        let regs = regs(&generator, timer());
+        assert!(regs.starts_with("reg! {\n    /// Alternate control\n    pub TIM1 CR_ALT => {\n"));
+        assert!(!regs.contains("pub TIM1 CR =>"));
+        assert!(index(&generator, timer()).ends_with(
+            "    /// Timer\n    pub mod TIM1 {\n        CR_ALT;\n        SR;\n    }\n}\n"
+        ));
+    }
+
+    #[test]
+    fn renames_after_prepare() {
+        let mut generator = Generator::new("tokens");
+        let device = generator.prepare(timer()).unwrap();
+        generator.rename_register("TIM1/CR", "SR");
+        let error = generator.generate_regs(&mut Vec::new(), &device, 1, 1).unwrap_err();
+        assert_eq!(error.to_string(), "device was prepared with different renames or filters");
+        let error = generator.prepare(timer()).unwrap_err();
+        assert_eq!(error.to_string(), "`TIM1/CR` and `TIM1/SR` are both generated as `TIM1_SR`");
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_output() {
+        fn timers() -> Device {
+            device(
+                r#"<peripheral><name>TIM1</name><description>Timer</description>
+                    <baseAddress>0x40000000</baseAddress>
+                    <registers>
+                        <register><name>CR</name><description>Control</description>
+                            <addressOffset>0</addressOffset></register>
+                        <register><name>CR_ALT</name><description>Alternate control</description>
+                            <alternateRegister>CR</alternateRegister>
+                            <addressOffset>0</addressOffset></register>
+                        <register><name>SR</name><description>Status</description>
+                            <addressOffset>4</addressOffset></register>
+                    </registers>
+                </peripheral>
+                <peripheral derivedFrom="TIM1"><name>TIM2</name>
+                    <baseAddress>0x40000400</baseAddress></peripheral>
+                <peripheral><name>GPIOA</name><description>GPIO</description>
+                    <baseAddress>0x48000000</baseAddress>
+                    <registers>
+                        <register><name>ODR[%s]</name><description>Output</description>
+                            <addressOffset>0</addressOffset><dim>2</dim>
+                            <dimIncrement>4</dimIncrement></register>
+                    </registers>
+                </peripheral>"#,
+            )
+        }
+        fn output(generator: &Generator<'_>) -> (String, Vec<String>) {
+            let device = generator.prepare(timers()).unwrap();
+            let mut regs = String::new();
+            for pool_number in 1..=3 {
+                let mut output = Vec::new();
+                generator.generate_regs(&mut output, &device, pool_number, 3).unwrap();
+                regs.push_str(&String::from_utf8(output).unwrap());
+            }
+            let mut outputs = vec![Vec::new(); 3];
+            generator.generate_shards(&mut outputs, &device).unwrap();
+            (regs, outputs.into_iter().map(|output| String::from_utf8(output).unwrap()).collect())
+        }
+        let mut generator = Generator::new("tokens");
+        generator.exclude_registers(&["TIM2/SR"]).register_traits_callback(
+            |peripheral, name, _| vec![format!("{peripheral}{}", name.len())],
+        );
+        let serial = output(&generator);
+        generator.parallel();
+        assert_eq!(output(&generator), serial);
+        assert!(serial.0.contains("pub TIM2 CR_ALT =>"));
+        assert!(serial.0.contains("pub GPIOA ODR_1 =>"));
+    }
+
+    fn instance(address: u32) -> Instance {
+        Instance {
+            description: Vec::new(),
+            peripheral_name: "TIM1".to_owned(),
+            name: vec!["CR".to_owned()],
+            address,
+            size: 32,
+            reset_value: 0,
+            access: None,
+            protection: None,
+        }
+    }
+
+    #[test]
+    fn bit_band() {
+        let sram = instance(0x2000_0010);
+        assert_eq!(bit_band_region(&sram), Some((0x2000_0000, 0x2200_0000)));
+        assert_eq!(bit_band_alias(&sram, 5, 1), Some(0x2200_0214));
+        let peripheral = instance(0x4000_0000);
+        assert_eq!(bit_band_region(&peripheral), Some((0x4000_0000, 0x4200_0000)));
+        assert_eq!(bit_band_alias(&peripheral, 3, 1), Some(0x4200_000C));
+        assert_eq!(bit_band_alias(&instance(0x4000_1004), 31, 1), Some(0x4202_00FC));
+        assert_eq!(bit_band_alias(&instance(0x400F_FFFC), 31, 1), Some(0x43FF_FFFC));
+        assert_eq!(bit_band_region(&instance(0x4010_0000)), None);
+        assert_eq!(bit_band_alias(&instance(0x4010_0000), 3, 1), None);
+        assert_eq!(bit_band_alias(&peripheral, 3, 2), None);
+    }
+
+    #[test]
+    fn bit_band_traits() {
+        let mut generator = Generator::new("tokens");
+        generator.bit_band();
+        let regs = regs(
+            &generator,
+            device(
+                r"<peripheral><name>TIM1</name><description>Timer</description>
+                    <baseAddress>0x40000000</baseAddress>
+                    <registers>
+                        <register><name>CR</name><description>Control</description>
+                            <addressOffset>0</addressOffset>
+                            <fields>
+                                <field><name>EN</name><description>Enable</description>
+                                    <bitOffset>3</bitOffset><bitWidth>1</bitWidth></field>
+                                <field><name>MODE</name><description>Mode</description>
+                                    <bitOffset>4</bitOffset><bitWidth>2</bitWidth></field>
+                            </fields>
+                        </register>
+                    </registers>
+                </peripheral>
+                <peripheral><name>GPIOA</name><description>GPIO</description>
+                    <baseAddress>0x48000000</baseAddress>
+                    <registers>
+                        <register><name>ODR</name><description>Output</description>
+                            <addressOffset>0</addressOffset></register>
+                    </registers>
+                </peripheral>",
+            ),
+        );
+        assert!(regs.contains("/// Bit-band alias: 0x4200_000C.\n            EN => {"));
+        assert!(regs.contains("/// Mode\n            MODE => {"));
+        let (timer, gpio) = regs.split_once("pub GPIOA ODR =>").unwrap();
+        assert!(timer.contains("traits => { RReg WReg RegBitBand };"));
+        assert!(gpio.contains("traits => { RReg WReg };"));
+    }
+
+    #[test]
+    fn peripheral_sharding_is_stable() {
+        let shards = |added: &str| {
+            use std::fmt::Write;
+            let mut peripherals = String::new();
+            for n in 0..8 {
+                let registers = if n == 3 { added } else { "" };
+                write!(
+                    peripherals,
+                    r"<peripheral><name>UART{n}</name><description>UART</description>
+                        <baseAddress>0x4000{n}000</baseAddress>
+                        <registers>
+                            <register><name>DR</name><description>Data</description>
+                                <addressOffset>0</addressOffset></register>
+                            {registers}
+                        </registers>
+                    </peripheral>"
+                )
+                .unwrap();
+            }
+            let mut generator = Generator::new("tokens");
+            generator.sharding(Sharding::Peripheral);
+            let device = generator.prepare(device(&peripherals)).unwrap();
+            let mut outputs = vec![Vec::new(); 4];
+            generator.generate_shards(&mut outputs, &device).unwrap();
+            outputs.into_iter().map(|output| String::from_utf8(output).unwrap()).collect::<Vec<_>>()
+        };
+        let old = shards("");
+        let new = shards(
+            r"<register><name>SR</name><description>Status</description>
+                <addressOffset>4</addressOffset></register>",
+        );
+        let changed = new.iter().position(|shard| shard.contains("pub UART3 SR =>")).unwrap();
+        assert!(new[changed].contains("pub UART3 DR =>"));
+        for (n, (old, new)) in old.iter().zip(&new).enumerate() {
+            assert_eq!(old == new, n != changed);
+        }
+        assert!(old.iter().filter(|shard| !shard.is_empty()).count() > 1);
+    }
+
+    #[test]
+    fn core_index() {
+        let mut generator = Generator::new("tokens");
+        generator.core("cm4", "cm4_tokens", "tokens", &["IPC*"]);
+        let index = index(
+            &generator,
+            device(
+                r"<peripheral><name>TIM1</name><description>Timer</description>
+                    <baseAddress>0x40000000</baseAddress>
+                    <registers>
+                        <register><name>CR</name><description>Control</description>
+                            <addressOffset>0</addressOffset></register>
+                    </registers>
+                </peripheral>
+                <peripheral><name>IPC1</name><description>IPC</description>
+                    <baseAddress>0x40001000</baseAddress>
+                    <registers>
+                        <register><name>SR</name><description>Status</description>
+                            <addressOffset>0</addressOffset></register>
+                    </registers>
+                </peripheral>",
+            ),
+        );
+        assert_eq!(
+            index,
+            r"reg::tokens! {
+    /// Defines an index of TEST MCU-level register tokens.
+    pub macro tokens;
+    super::inner;
+    crate::reg;
+    /// Timer
+    pub mod TIM1 {
+        CR;
+    }
+}
+
+reg::tokens! {
+    /// Defines an index of TEST cm4 core-private register tokens.
+    pub macro cm4_tokens;
+    use macro tokens;
+    super::inner;
+    crate::reg;
+    /// IPC
+    pub mod IPC1 {
+        SR;
+    }
+}
+"
+        );
+    }
+
+    #[test]
+    fn grouped_index() {
+        let peripherals = |name: &str| {
+            format!(
+                r"<peripheral><name>UART1</name><description>UART</description>
+                    <groupName>UART</groupName><baseAddress>0x40001000</baseAddress>
+                    <registers>
+                        <register><name>DR</name><description>Data</description>
+                            <addressOffset>0</addressOffset></register>
+                    </registers>
+                </peripheral>
+                <peripheral><name>{name}</name><description>Timer</description>
+                    <baseAddress>0x40000000</baseAddress>
+                    <registers>
+                        <register><name>CR</name><description>Control</description>
+                            <addressOffset>0</addressOffset></register>
+                    </registers>
+                </peripheral>"
+            )
+        };
+        let mut generator = Generator::new("tokens");
+        generator.group_peripherals();
+        assert!(index(&generator, device(&peripherals("TIM1"))).ends_with(
+            r"    pub mod UART {
+        /// UART
+        pub mod UART1 {
+            DR;
+        }
+    }
+    /// Timer
+    pub mod TIM1 {
+        CR;
+    }
+}
+"
+        ));
+        let device = generator.prepare(device(&peripherals("UART"))).unwrap();
+        let error = generator.generate_index(&mut Vec::new(), &device).unwrap_err();
+        assert_eq!(error.to_string(), "peripheral group `UART` collides with peripheral `UART`");
+    }
+}
@@ -0,0 +1,82 @@
+/// Matches `name` against the glob `pattern`.
+///
+/// Supports `*` for any sequence of characters, `?` for any single character,
+/// and `[...]` for a character class with optional `a-z` ranges and `!`
+/// negation. A pattern equal to `name` always matches, so array names like
+/// `TIM[%s]` can be used as is.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    if pattern == name {
+        return true;
+    }
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            Some('[') => {
+                if let Some((matched, len)) = match_class(&pattern[p..], name[n]) {
+                    if matched {
+                        p += len;
+                        n += 1;
+                        continue;
+                    }
+                } else if name[n] == '[' {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+            }
+            Some(&c) if c == name[n] => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn match_class(class: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negate = matches!(class.get(i), Some('!' | '^'));
+    if negate {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        match class.get(i)? {
+            ']' if !first => break,
+            &start => {
+                if class.get(i + 1) == Some(&'-') && class.get(i + 2).map_or(false, |&c| c != ']') {
+                    matched |= (start..=class[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    matched |= start == c;
+                    i += 1;
+                }
+            }
+        }
+        first = false;
+    }
+    Some((matched != negate, i + 1))
+}
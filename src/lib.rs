@@ -3,7 +3,7 @@
 //! # Documentation
 //!
 //! - [Drone Book](https://book.drone-os.com/)
-//! - [API documentation](https://api.drone-os.com/drone-svd/0.15/)
+//! - [API documentation](https://api.drone-os.com/drone-svd/0.16/)
 //!
 //! # Usage
 //!
@@ -11,7 +11,7 @@
 //!
 //! ```toml
 //! [dependencies]
-//! drone-svd = { version = "0.15.0" }
+//! drone-svd = { version = "0.16.0" }
 //! ```
 
 #![warn(missing_docs, unsafe_op_in_unsafe_fn)]
@@ -24,13 +24,37 @@
     clippy::must_use_candidate
 )]
 
+mod dedup;
 mod device;
+mod diff;
 mod generator;
+mod glob;
+mod lookup;
+mod merge;
+mod naming;
+mod patch;
+mod periph_map;
+mod resolve;
+mod secure;
+mod tokens;
 mod traverse;
+mod validate;
+mod value;
 mod variant;
 
-pub use self::generator::Generator;
-pub use device::{Access, Device, Field, Peripheral, Register};
+pub use self::diff::{Change, ChangeKind, DeviceDiff, Element};
+pub use self::generator::{FieldContext, Generator, PreparedDevice, RegisterContext, Sharding};
+pub use self::lookup::AddressIndex;
+pub use self::merge::MergePolicy;
+pub use self::patch::Patch;
+pub use self::resolve::{ResolvedDevice, ResolvedField, ResolvedPeripheral, ResolvedRegister};
+pub use self::tokens::{ApiChange, ApiChangeKind, ApiReport, RegToken};
+pub use self::validate::{Diagnostic, Severity};
+pub use self::value::{DecodedField, FieldValue};
+pub use device::{
+    Access, AddressBlock, Device, EnumValue, EnumeratedValue, EnumeratedValues, Field, Peripheral,
+    Protection, Register, Usage, WriteConstraint, WriteConstraintRange,
+};
 use eyre::Result;
 use std::fs::File;
 use std::io::prelude::*;
@@ -44,3 +68,11 @@ pub fn parse<P: AsRef<Path>>(path: P) -> Result<Device> {
     input.read_to_string(&mut xml)?;
     Ok(quick_xml::de::from_reader(xml.as_bytes())?)
 }
+
+/// Parse the patch file at `path`.
+pub fn parse_patch<P: AsRef<Path>>(path: P) -> Result<Patch> {
+    let mut input = BufReader::new(File::open(path)?);
+    let mut toml = String::new();
+    input.read_to_string(&mut toml)?;
+    toml.parse()
+}
@@ -0,0 +1,81 @@
+use crate::{ResolvedDevice, ResolvedRegister};
+use std::ops::Range;
+
+/// Index mapping absolute addresses to register instances.
+///
+/// Alternate peripherals and registers share their addresses with the
+/// originals, so a single address may map to several register instances.
+#[derive(Clone, Debug)]
+pub struct AddressIndex<'a> {
+    registers: Vec<(Range<u64>, &'a ResolvedRegister)>,
+    max_len: u64,
+}
+
+impl ResolvedDevice {
+    /// Builds an index for looking up register instances by address.
+    pub fn address_index(&self) -> AddressIndex<'_> {
+        let mut registers =
+            self.regs().map(|register| (register.address_range(), register)).collect::<Vec<_>>();
+        registers.sort_by_key(|(range, _)| range.start);
+        let max_len = registers.iter().map(|(range, _)| range.end - range.start).max().unwrap_or(0);
+        AddressIndex { registers, max_len }
+    }
+}
+
+impl<'a> AddressIndex<'a> {
+    /// Returns an iterator over register instances covering `address`.
+    pub fn lookup(&self, address: u32) -> impl Iterator<Item = &'a ResolvedRegister> + '_ {
+        let address = u64::from(address);
+        self.overlapping(address..address + 1)
+    }
+
+    /// Returns an iterator over register instances overlapping `range`.
+    pub fn lookup_range(
+        &self,
+        range: Range<u32>,
+    ) -> impl Iterator<Item = &'a ResolvedRegister> + '_ {
+        self.overlapping(u64::from(range.start)..u64::from(range.end))
+    }
+
+    fn overlapping(&self, range: Range<u64>) -> impl Iterator<Item = &'a ResolvedRegister> + '_ {
+        let first = self.registers.partition_point(|(r, _)| r.start + self.max_len <= range.start);
+        self.registers[first..]
+            .iter()
+            .take_while(move |(r, _)| r.start < range.end)
+            .filter(move |(r, _)| r.end > range.start)
+            .map(|&(_, register)| register)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, ResolvedRegister};
+
+    #[test]
+    fn lookup_top_of_address_space() {
+        let device: Device = quick_xml::de::from_str(
+            r"<device>
+                <name>TEST</name>
+                <size>32</size>
+                <resetValue>0</resetValue>
+                <peripherals>
+                    <peripheral>
+                        <name>TOP</name>
+                        <baseAddress>0xFFFFFFFC</baseAddress>
+                        <registers>
+                            <register><name>REG</name><addressOffset>0</addressOffset></register>
+                        </registers>
+                    </peripheral>
+                </peripherals>
+            </device>",
+        )
+        .unwrap();
+        let device = device.resolve().unwrap();
+        let index = device.address_index();
+        let found =
+            |address| index.lookup(address).map(ResolvedRegister::full_path).collect::<Vec<_>>();
+        assert_eq!(found(0xFFFF_FFFF), ["TOP/REG"]);
+        assert_eq!(found(0xFFFF_FFFC), ["TOP/REG"]);
+        assert!(found(0xFFFF_FFFB).is_empty());
+    }
+}
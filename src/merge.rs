@@ -0,0 +1,67 @@
+use crate::Device;
+use crate::device::RegisterTree;
+use eyre::{bail, Result};
+use indexmap::IndexMap;
+
+/// Policy for resolving peripheral name conflicts in [`Device::merge`].
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MergePolicy {
+    /// Fail on the first conflicting peripheral.
+    Error,
+    /// Keep the existing peripheral and discard the other one.
+    Keep,
+    /// Replace the existing peripheral in place with the other one.
+    Replace,
+    /// Add registers and clusters of the other peripheral to the existing one.
+    /// Fails if both peripherals have a register with the same name.
+    MergeRegisters,
+}
+
+impl Device {
+    /// Merges peripherals of the device `other` into this device.
+    ///
+    /// New peripherals are appended after the existing ones, and conflicting
+    /// peripherals are resolved according to `policy` without changing their
+    /// position. Device-level defaults of `other` are ignored. The device is
+    /// left unchanged on error.
+    pub fn merge(&mut self, other: Device, policy: MergePolicy) -> Result<()> {
+        let mut peripherals = self.peripherals.clone();
+        for (name, peripheral) in other.peripherals {
+            let Some(existing) = peripherals.get_mut(&name) else {
+                peripherals.insert(name, peripheral);
+                continue;
+            };
+            match policy {
+                MergePolicy::Error => bail!("peripheral `{name}` already exists"),
+                MergePolicy::Keep => {}
+                MergePolicy::Replace => *existing = peripheral,
+                MergePolicy::MergeRegisters => {
+                    merge_tree(&mut existing.registers, peripheral.registers, &name)?;
+                }
+            }
+        }
+        self.peripherals = peripherals;
+        Ok(())
+    }
+}
+
+fn merge_tree(
+    tree: &mut IndexMap<String, RegisterTree>,
+    other: IndexMap<String, RegisterTree>,
+    path: &str,
+) -> Result<()> {
+    for (name, node) in other {
+        let path = format!("{path}/{name}");
+        match (tree.get_mut(&name), node) {
+            (None, node) => {
+                tree.insert(name, node);
+            }
+            (Some(RegisterTree::Cluster(cluster)), RegisterTree::Cluster(other)) => {
+                merge_tree(&mut cluster.register, other.register, &path)?;
+            }
+            (Some(_), _) => bail!("register `{path}` already exists"),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use crate::Device;
+use crate::generator::{generate_peripheral, Generator};
+use crate::traverse::dim_name;
+use eyre::{bail, Result};
+use std::collections::{HashMap, HashSet};
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+#[derive(Default)]
+pub(crate) struct Naming<'a> {
+    pub(crate) peripherals: HashMap<&'a str, &'a str>,
+    pub(crate) registers: HashMap<&'a str, &'a str>,
+    pub(crate) fields: HashMap<&'a str, &'a str>,
+}
+
+impl Naming<'_> {
+    /// Returns the generated name of the peripheral instance `name`.
+    pub(crate) fn peripheral(&self, name: &str) -> String {
+        sanitize(self.peripherals.get(name).copied().unwrap_or(name))
+    }
+
+    /// Returns the generated name of the register instance at the path `name`
+    /// inside the peripheral instance `peripheral_name`.
+    pub(crate) fn register(&self, peripheral_name: &str, name: &[String]) -> String {
+        let path = format!("{peripheral_name}/{}", name.join("/"));
+        if let Some(new_name) = self.registers.get(path.as_str()) {
+            return sanitize(new_name);
+        }
+        sanitize_path(name)
+    }
+
+    /// Returns the generated name of the field instance `field_name` of the
+    /// register instance at the path `name`.
+    pub(crate) fn field(&self, peripheral_name: &str, name: &[String], field_name: &str) -> String {
+        let path = format!("{peripheral_name}/{}/{field_name}", name.join("/"));
+        sanitize(self.fields.get(path.as_str()).copied().unwrap_or(field_name))
+    }
+
+    /// Returns all renames sorted by their source paths.
+    pub(crate) fn renames(&self) -> Vec<(String, String)> {
+        let mut renames = [&self.peripherals, &self.registers, &self.fields]
+            .into_iter()
+            .flatten()
+            .map(|(&from, &to)| (from.to_owned(), to.to_owned()))
+            .collect::<Vec<_>>();
+        renames.sort();
+        renames
+    }
+}
+
+impl Generator<'_> {
+    /// Checks that distinct elements of the description don't clash after
+    /// renaming and sanitization.
+    pub(crate) fn check_names(&self, device: &Device) -> Result<()> {
+        let mut peripherals = HashMap::new();
+        for peripheral in device.peripherals.values() {
+            if self.exclude_peripherals.iter().any(|&name| name == peripheral.name) {
+                continue;
+            }
+            for peripheral_n in 0..peripheral.dim.unwrap_or(1) {
+                let name = dim_name(peripheral_n, &peripheral.name);
+                check_clash(&mut peripherals, self.naming.peripheral(&name), name)?;
+            }
+        }
+        let mut registers = HashMap::new();
+        let mut generated = HashSet::new();
+        for peripheral in device.peripherals.values() {
+            if self.exclude_peripherals.iter().any(|&name| name == peripheral.name) {
+                continue;
+            }
+            generate_peripheral(device, peripheral, &mut generated, |instances| {
+                for (register, instance) in &instances {
+                    let (peripheral_name, name) = (&instance.peripheral_name, &instance.name);
+                    if !self.filters.register(peripheral_name, name) {
+                        continue;
+                    }
+                    let path = format!("{peripheral_name}/{}", name.join("/"));
+                    let new_name = format!(
+                        "{}_{}",
+                        self.naming.peripheral(peripheral_name),
+                        self.naming.register(peripheral_name, name)
+                    );
+                    check_clash(&mut registers, new_name, path.clone())?;
+                    let mut fields = HashMap::new();
+                    for field in &register.fields {
+                        for number in 0..field.dim.unwrap_or(1) {
+                            let field_name = dim_name(number, &field.name);
+                            if !self.filters.field(peripheral_name, name, &field_name) {
+                                continue;
+                            }
+                            let new_name = self.naming.field(peripheral_name, name, &field_name);
+                            check_clash(&mut fields, new_name, format!("{path}/{field_name}"))?;
+                        }
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+fn check_clash(names: &mut HashMap<String, String>, new_name: String, path: String) -> Result<()> {
+    if let Some(other) = names.get(&new_name) {
+        if *other != path {
+            bail!("`{other}` and `{path}` are both generated as `{new_name}`");
+        }
+    } else {
+        names.insert(new_name, path);
+    }
+    Ok(())
+}
+
+/// Joins cluster and register names into a sanitized identifier.
+pub(crate) fn sanitize_path(path: &[String]) -> String {
+    sanitize(&path.join("_"))
+}
+
+/// Turns `name` into a valid identifier, which is also not a keyword after
+/// conversion to snake case.
+pub(crate) fn sanitize(name: &str) -> String {
+    let mut name = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect::<String>();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    if KEYWORDS.contains(&name.to_ascii_lowercase().as_str()) {
+        name.push('_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize, sanitize_path};
+
+    #[test]
+    fn keywords() {
+        assert_eq!(sanitize("IN"), "IN_");
+        assert_eq!(sanitize("Type"), "Type_");
+        assert_eq!(sanitize("INT"), "INT");
+        assert_eq!(sanitize("0CR"), "_0CR");
+        assert_eq!(sanitize("CR-1"), "CR_1");
+    }
+
+    #[test]
+    fn keywords_across_segments() {
+        let path = |names: &[&str]| names.iter().map(|&name| name.to_owned()).collect::<Vec<_>>();
+        assert_eq!(sanitize_path(&path(&["CH", "IN"])), "CH_IN");
+        assert_eq!(sanitize_path(&path(&["IN", "CR"])), "IN_CR");
+        assert_eq!(sanitize_path(&path(&["IN"])), "IN_");
+        assert_eq!(sanitize_path(&path(&["0", "CR"])), "_0_CR");
+    }
+}
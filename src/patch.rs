@@ -0,0 +1,529 @@
+use crate::device::{tree_rekey, tree_remove_regs, tree_select_regs};
+use crate::glob::glob_match;
+use crate::{Access, Device, Field, Peripheral, Register};
+use eyre::{bail, eyre, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::mem;
+use std::str::FromStr;
+
+/// Declarative set of modifications to a device description.
+///
+/// A patch is a TOML document with a list of `[[patch]]` entries. Each entry
+/// selects peripherals, registers, or fields with glob patterns, and either
+/// deletes them, sets their properties, or adds a new child element to them:
+///
+/// ```toml
+/// [[patch]]
+/// peripheral = "USART*"
+/// register = "SR"
+/// field = "*IE"
+/// set = { access = "read-write" }
+///
+/// [[patch]]
+/// peripheral = "GPIO?"
+/// register = "MODER"
+/// add = { name = "MODER16", bit_offset = 30, bit_width = 2 }
+///
+/// [[patch]]
+/// peripheral = "DBGMCU"
+/// delete = true
+/// ```
+///
+/// Register patterns may include cluster names separated by `/`. An entry
+/// without selectors can only add a new peripheral, and adding a register
+/// requires `address_offset`. The `name` property in `set` renames the
+/// element and updates `derivedFrom` and alternate references to it.
+#[derive(Clone, Debug)]
+pub struct Patch {
+    entries: Vec<(Option<usize>, Entry)>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatchFile {
+    #[serde(default)]
+    patch: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    peripheral: Option<String>,
+    register: Option<String>,
+    field: Option<String>,
+    #[serde(default)]
+    delete: bool,
+    set: Option<Properties>,
+    add: Option<Properties>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Properties {
+    name: Option<String>,
+    description: Option<String>,
+    derived_from: Option<String>,
+    base_address: Option<u32>,
+    address_offset: Option<u32>,
+    size: Option<u32>,
+    reset_value: Option<u32>,
+    access: Option<Access>,
+    bit_offset: Option<u32>,
+    bit_width: Option<u32>,
+    force_bits: Option<bool>,
+}
+
+impl FromStr for Patch {
+    type Err = eyre::Error;
+
+    fn from_str(src: &str) -> Result<Self> {
+        let PatchFile { patch } = toml::from_str(src)?;
+        let mut lines = src
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.trim_start().starts_with("[[patch]]"))
+            .map(|(i, _)| i + 1)
+            .collect::<Vec<_>>();
+        if lines.len() != patch.len() {
+            lines.clear();
+        }
+        Ok(Self {
+            entries: patch
+                .into_iter()
+                .enumerate()
+                .map(|(i, entry)| (lines.get(i).copied(), entry))
+                .collect(),
+        })
+    }
+}
+
+impl Device {
+    /// Applies the patch `patch` to the device.
+    ///
+    /// Fails if a selector of an entry doesn't match any element, or if an
+    /// element is added or renamed with an existing name. The device is left
+    /// unchanged on failure.
+    pub fn patch(&mut self, patch: &Patch) -> Result<()> {
+        let mut device = self.clone();
+        for (i, (line, entry)) in patch.entries.iter().enumerate() {
+            entry.apply(&mut device).map_err(|err| match line {
+                Some(line) => err.wrap_err(format!("patch at line {line} failed")),
+                None => err.wrap_err(format!("patch entry #{} failed", i + 1)),
+            })?;
+        }
+        *self = device;
+        Ok(())
+    }
+}
+
+impl Entry {
+    fn apply(&self, device: &mut Device) -> Result<()> {
+        match (self.delete, &self.set, &self.add) {
+            (true, None, None) | (false, Some(_), None) | (false, None, Some(_)) => {}
+            _ => bail!("exactly one of `delete`, `set`, or `add` must be specified"),
+        }
+        match (&self.peripheral, &self.register, &self.field) {
+            (None, None, None) => self.apply_device(device),
+            (Some(peripheral), None, None) => self.apply_peripherals(device, peripheral),
+            (Some(peripheral), Some(register), None) => {
+                self.apply_registers(device, peripheral, register)
+            }
+            (Some(peripheral), Some(register), Some(field)) => {
+                self.apply_fields(device, peripheral, register, field)
+            }
+            _ => bail!("`register` requires `peripheral`, and `field` requires `register`"),
+        }
+    }
+
+    fn apply_device(&self, device: &mut Device) -> Result<()> {
+        let Some(properties) = &self.add else {
+            bail!("only `add` is allowed without selectors");
+        };
+        if properties.base_address.is_none() {
+            bail!("missing `base_address`");
+        }
+        let mut peripheral = Peripheral { name: properties.new_name()?, ..Peripheral::default() };
+        if device.peripherals.contains_key(&peripheral.name) {
+            bail!("peripheral `{}` already exists", peripheral.name);
+        }
+        properties.set_peripheral(&mut peripheral)?;
+        device.add_periph(peripheral);
+        Ok(())
+    }
+
+    fn apply_peripherals(&self, device: &mut Device, pattern: &str) -> Result<()> {
+        if self.delete {
+            let count = device.peripherals.len();
+            device.peripherals.retain(|_, peripheral| !glob_match(pattern, &peripheral.name));
+            if device.peripherals.len() == count {
+                bail!("peripheral `{pattern}` not found");
+            }
+            return Ok(());
+        }
+        let peripherals = device.select_periphs(pattern);
+        if peripherals.len() == 0 {
+            bail!("peripheral `{pattern}` not found");
+        }
+        if let Some(properties) = &self.set {
+            properties.ensure_single(peripherals.len())?;
+            let mut renamed = HashMap::new();
+            for peripheral in peripherals {
+                let name = peripheral.name.clone();
+                properties.set_peripheral(peripheral)?;
+                if peripheral.name != name {
+                    renamed.insert(name, peripheral.name.clone());
+                }
+            }
+            for name in renamed.values() {
+                if device.peripherals.contains_key(name) {
+                    bail!("peripheral `{name}` already exists");
+                }
+            }
+            device.peripherals = mem::take(&mut device.peripherals)
+                .into_iter()
+                .map(|(_, peripheral)| (peripheral.name.clone(), peripheral))
+                .collect();
+            for peripheral in device.peripherals.values_mut() {
+                for reference in
+                    [&mut peripheral.derived_from, &mut peripheral.alternate_peripheral]
+                {
+                    if let Some(name) = reference.as_ref().and_then(|name| renamed.get(name)) {
+                        *reference = Some(name.clone());
+                    }
+                }
+            }
+        } else if let Some(properties) = &self.add {
+            if properties.address_offset.is_none() {
+                bail!("missing `address_offset`");
+            }
+            for peripheral in peripherals {
+                let mut register = Register { name: properties.new_name()?, ..Register::default() };
+                if peripheral.registers.contains_key(&register.name) {
+                    bail!("register `{}` already exists", register.name);
+                }
+                properties.set_register(&mut register)?;
+                peripheral.add_reg(register);
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_registers(
+        &self,
+        device: &mut Device,
+        peripheral_pattern: &str,
+        pattern: &str,
+    ) -> Result<()> {
+        let pattern = pattern.split('/').collect::<Vec<_>>();
+        let mut count = 0;
+        for peripheral in device.peripherals.values_mut() {
+            if !glob_match(peripheral_pattern, &peripheral.name) {
+                continue;
+            }
+            if self.delete {
+                count += tree_remove_regs(&mut peripheral.registers, &pattern);
+                continue;
+            }
+            let mut registers = Vec::new();
+            tree_select_regs(&mut peripheral.registers, &pattern, &mut registers);
+            count += registers.len();
+            if let Some(properties) = &self.set {
+                properties.ensure_single(registers.len())?;
+                for register in registers {
+                    properties.set_register(register)?;
+                }
+                tree_rekey(&mut peripheral.registers)?;
+            } else if let Some(properties) = &self.add {
+                if properties.bit_offset.is_none() || properties.bit_width.is_none() {
+                    bail!("missing `bit_offset` or `bit_width`");
+                }
+                for register in registers {
+                    let mut field = Field { name: properties.new_name()?, ..Field::default() };
+                    if register.fields.iter().any(|other| other.name == field.name) {
+                        bail!("field `{}` already exists", field.name);
+                    }
+                    properties.set_field(&mut field)?;
+                    register.add_field(field);
+                }
+            }
+        }
+        if count == 0 {
+            bail!("register `{peripheral_pattern}/{}` not found", pattern.join("/"));
+        }
+        Ok(())
+    }
+
+    fn apply_fields(
+        &self,
+        device: &mut Device,
+        peripheral_pattern: &str,
+        register_pattern: &str,
+        pattern: &str,
+    ) -> Result<()> {
+        if self.add.is_some() {
+            bail!("`add` is not allowed for fields");
+        }
+        let register_pattern = register_pattern.split('/').collect::<Vec<_>>();
+        let mut count = 0;
+        for peripheral in device.peripherals.values_mut() {
+            if !glob_match(peripheral_pattern, &peripheral.name) {
+                continue;
+            }
+            let mut registers = Vec::new();
+            tree_select_regs(&mut peripheral.registers, &register_pattern, &mut registers);
+            for register in registers {
+                if self.delete {
+                    let len = register.fields.len();
+                    register.fields.retain(|field| !glob_match(pattern, &field.name));
+                    count += len - register.fields.len();
+                } else if let Some(properties) = &self.set {
+                    let fields = register.select_fields(pattern);
+                    count += fields.len();
+                    properties.ensure_single(fields.len())?;
+                    for field in fields {
+                        properties.set_field(field)?;
+                    }
+                    if let Some(name) = &properties.name {
+                        if register.fields.iter().filter(|field| field.name == *name).count() > 1 {
+                            bail!("field `{name}` already exists");
+                        }
+                    }
+                }
+            }
+        }
+        if count == 0 {
+            bail!(
+                "field `{peripheral_pattern}/{}/{pattern}` not found",
+                register_pattern.join("/")
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Properties {
+    fn new_name(&self) -> Result<String> {
+        self.name.clone().ok_or_else(|| eyre!("missing `name`"))
+    }
+
+    fn ensure_single(&self, count: usize) -> Result<()> {
+        if self.name.is_some() && count > 1 {
+            bail!("`name` can't be set for multiple elements");
+        }
+        Ok(())
+    }
+
+    fn set_peripheral(&self, peripheral: &mut Peripheral) -> Result<()> {
+        reject("peripheral", &[
+            ("address_offset", self.address_offset.is_some()),
+            ("bit_offset", self.bit_offset.is_some()),
+            ("bit_width", self.bit_width.is_some()),
+            ("force_bits", self.force_bits.is_some()),
+        ])?;
+        set(&mut peripheral.name, self.name.as_ref());
+        set_opt(&mut peripheral.description, self.description.as_ref());
+        set_opt(&mut peripheral.derived_from, self.derived_from.as_ref());
+        set(&mut peripheral.base_address, self.base_address.as_ref());
+        set_opt(&mut peripheral.size, self.size.as_ref());
+        set_opt(&mut peripheral.reset_value, self.reset_value.as_ref());
+        set_opt(&mut peripheral.access, self.access.as_ref());
+        Ok(())
+    }
+
+    fn set_register(&self, register: &mut Register) -> Result<()> {
+        reject("register", &[
+            ("derived_from", self.derived_from.is_some()),
+            ("base_address", self.base_address.is_some()),
+            ("bit_offset", self.bit_offset.is_some()),
+            ("bit_width", self.bit_width.is_some()),
+            ("force_bits", self.force_bits.is_some()),
+        ])?;
+        set(&mut register.name, self.name.as_ref());
+        set(&mut register.description, self.description.as_ref());
+        set(&mut register.address_offset, self.address_offset.as_ref());
+        set_opt(&mut register.size, self.size.as_ref());
+        set_opt(&mut register.reset_value, self.reset_value.as_ref());
+        set_opt(&mut register.access, self.access.as_ref());
+        Ok(())
+    }
+
+    fn set_field(&self, field: &mut Field) -> Result<()> {
+        reject("field", &[
+            ("derived_from", self.derived_from.is_some()),
+            ("base_address", self.base_address.is_some()),
+            ("address_offset", self.address_offset.is_some()),
+            ("size", self.size.is_some()),
+            ("reset_value", self.reset_value.is_some()),
+        ])?;
+        if self.bit_offset.is_some() || self.bit_width.is_some() {
+            field.bit_offset = Some(self.bit_offset.unwrap_or_else(|| field.bit_offset()));
+            field.bit_width = Some(self.bit_width.unwrap_or_else(|| field.bit_width()));
+            field.lsb = None;
+            field.msb = None;
+            field.bit_range = None;
+        }
+        set(&mut field.name, self.name.as_ref());
+        set(&mut field.description, self.description.as_ref());
+        set_opt(&mut field.access, self.access.as_ref());
+        set(&mut field.force_bits, self.force_bits.as_ref());
+        Ok(())
+    }
+}
+
+fn set<T: Clone>(target: &mut T, value: Option<&T>) {
+    if let Some(value) = value {
+        *target = value.clone();
+    }
+}
+
+fn set_opt<T: Clone>(target: &mut Option<T>, value: Option<&T>) {
+    if let Some(value) = value {
+        *target = Some(value.clone());
+    }
+}
+
+fn reject(element: &str, properties: &[(&str, bool)]) -> Result<()> {
+    for (property, is_set) in properties {
+        if *is_set {
+            bail!("`{property}` is not applicable to a {element}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, Patch};
+
+    fn device() -> Device {
+        quick_xml::de::from_str(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue><peripherals>
+                <peripheral><name>TIM1</name><description>t</description>
+                    <baseAddress>0x1000</baseAddress>
+                    <registers>
+                        <register><name>CR</name><description>r</description>
+                            <addressOffset>0</addressOffset>
+                            <fields><field><name>EN</name><description>f</description>
+                                <bitOffset>0</bitOffset><bitWidth>1</bitWidth></field></fields>
+                        </register>
+                        <register><name>CR_ALT</name><description>r</description>
+                            <alternateRegister>CR</alternateRegister>
+                            <addressOffset>0</addressOffset></register>
+                        <register><name>SR</name><description>r</description>
+                            <addressOffset>4</addressOffset></register>
+                    </registers>
+                </peripheral>
+                <peripheral derivedFrom='TIM1'><name>TIM2</name><description>t</description>
+                    <baseAddress>0x2000</baseAddress></peripheral>
+            </peripherals></device>",
+        )
+        .unwrap()
+    }
+
+    fn patch(device: &mut Device, src: &str) -> Result<(), String> {
+        device.patch(&src.parse::<Patch>().unwrap()).map_err(|err| format!("{err:#}"))
+    }
+
+    #[test]
+    fn rename_updates_references() {
+        let mut device = device();
+        patch(
+            &mut device,
+            r#"
+                [[patch]]
+                peripheral = "TIM1"
+                set = { name = "TIM3" }
+
+                [[patch]]
+                peripheral = "TIM3"
+                register = "CR"
+                set = { name = "CR1" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(device.peripherals.keys().collect::<Vec<_>>(), ["TIM3", "TIM2"]);
+        assert_eq!(device.peripherals["TIM2"].derived_from.as_deref(), Some("TIM3"));
+        let registers = &device.peripherals["TIM3"].registers;
+        assert_eq!(registers.keys().collect::<Vec<_>>(), ["CR1", "CR_ALT", "SR"]);
+        let alternate = &registers["CR_ALT"].unwrap_register_ref().alternate_register;
+        assert_eq!(alternate.as_deref(), Some("CR1"));
+    }
+
+    #[test]
+    fn rename_collisions() {
+        let mut device = device();
+        assert_eq!(
+            patch(&mut device, "[[patch]]\nperipheral = \"TIM1\"\nset = { name = \"TIM2\" }"),
+            Err("patch at line 1 failed: peripheral `TIM2` already exists".into())
+        );
+        assert_eq!(
+            patch(
+                &mut device,
+                "[[patch]]\nperipheral = \"TIM1\"\nregister = \"CR\"\nset = { name = \"SR\" }"
+            ),
+            Err("patch at line 1 failed: register `SR` already exists".into())
+        );
+        assert_eq!(device.peripherals.keys().collect::<Vec<_>>(), ["TIM1", "TIM2"]);
+        assert_eq!(device.peripherals["TIM1"].registers.len(), 3);
+    }
+
+    #[test]
+    fn failed_patch_leaves_device_unchanged() {
+        let mut device = device();
+        let result = patch(
+            &mut device,
+            r#"
+                [[patch]]
+                peripheral = "TIM1"
+                register = "SR"
+                delete = true
+
+                [[patch]]
+                peripheral = "TIM1"
+                register = "MISSING"
+                delete = true
+            "#,
+        );
+        assert_eq!(result, Err("patch at line 7 failed: register `TIM1/MISSING` not found".into()));
+        assert!(device.peripherals["TIM1"].registers.contains_key("SR"));
+    }
+
+    #[test]
+    fn add_collisions() {
+        let mut device = device();
+        assert_eq!(
+            patch(&mut device, "[[patch]]\nadd = { name = \"TIM1\", base_address = 0x3000 }"),
+            Err("patch at line 1 failed: peripheral `TIM1` already exists".into())
+        );
+        assert_eq!(
+            patch(
+                &mut device,
+                "[[patch]]\nperipheral = \"TIM1\"\nadd = { name = \"SR\", address_offset = 0x40 }"
+            ),
+            Err("patch at line 1 failed: register `SR` already exists".into())
+        );
+        assert_eq!(
+            patch(
+                &mut device,
+                "[[patch]]\nperipheral = \"TIM1\"\nregister = \"CR\"\nadd = { name = \"EN\", \
+                 bit_offset = 1, bit_width = 1 }"
+            ),
+            Err("patch at line 1 failed: field `EN` already exists".into())
+        );
+        assert_eq!(device.peripherals["TIM1"].registers.len(), 3);
+        assert_eq!(
+            device.peripherals["TIM1"].registers["CR"].unwrap_register_ref().fields.len(),
+            1
+        );
+    }
+
+    #[test]
+    fn add_register_requires_address_offset() {
+        let mut device = device();
+        assert_eq!(
+            patch(&mut device, "[[patch]]\nperipheral = \"TIM1\"\nadd = { name = \"DR\" }"),
+            Err("patch at line 1 failed: missing `address_offset`".into())
+        );
+    }
+}
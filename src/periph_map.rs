@@ -0,0 +1,450 @@
+use crate::generator::{Generator, PreparedDevice};
+use crate::glob::glob_match;
+use crate::naming::{sanitize, sanitize_path, Naming};
+use crate::traverse::dim_name;
+use crate::{Access, Device, ResolvedPeripheral, ResolvedRegister};
+use eyre::{bail, Result};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+use std::io::Write;
+
+type Registers<'a> = IndexMap<&'a [String], (&'a ResolvedRegister, usize, Fields<'a>)>;
+
+type Fields<'a> = IndexMap<&'a str, (u32, Access, usize)>;
+
+impl Generator<'_> {
+    /// Generates `periph!` and `periph::map!` invocations for peripheral
+    /// families.
+    ///
+    /// Families are configured with [`Generator::periph_family`]. The rest of
+    /// the peripherals are grouped automatically by `groupName`, `derivedFrom`
+    /// references, peripheral arrays, and structural equality, and named after
+    /// the common prefix of the member names without a partial instance
+    /// number. The generic peripheral of a family contains all registers and
+    /// fields of its members, and those missing in some of the members are
+    /// marked `Option`. Registers differing in size or access, and fields
+    /// differing in width or access between the members are left out. Fails
+    /// if two families get the same name.
+    ///
+    /// `reg_path` is the path to the register tokens, and `periph_path` is the
+    /// path to the module with the generated `periph!` invocations.
+    pub fn generate_periph_maps(
+        &self,
+        output: &mut impl Write,
+        device: &PreparedDevice,
+        reg_path: &str,
+        periph_path: &str,
+    ) -> Result<()> {
+        let device = self.prepared(device)?;
+        let mut resolved = device.resolve()?;
+        for peripheral in &mut resolved.peripherals {
+            let name = &peripheral.name;
+            peripheral.registers.retain(|register| self.filters.register(name, &register.path));
+            for register in &mut peripheral.registers {
+                let path = &register.path;
+                register.fields.retain(|field| self.filters.field(name, path, &field.name));
+            }
+        }
+        for (name, members) in self.periph_families(device)? {
+            let members = resolved
+                .peripherals
+                .iter()
+                .filter(|peripheral| members.contains(&peripheral.name))
+                .collect::<Vec<_>>();
+            let registers = collect_registers(&members);
+            generate_periph(output, &name, members.len(), &registers)?;
+            for member in members {
+                writeln!(output)?;
+                generate_periph_map(
+                    output,
+                    &name,
+                    member,
+                    &registers,
+                    &self.naming,
+                    reg_path,
+                    periph_path,
+                )?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+
+    fn periph_families(&self, device: &Device) -> Result<Vec<(String, Vec<String>)>> {
+        let mut instances = IndexMap::new();
+        for peripheral in device.peripherals.values() {
+            if self.exclude_peripherals.iter().any(|&name| name == peripheral.name) {
+                continue;
+            }
+            let names = (0..peripheral.dim.unwrap_or(1))
+                .map(|peripheral_n| dim_name(peripheral_n, &peripheral.name))
+                .collect::<Vec<_>>();
+            instances.insert(peripheral.name.as_str(), names);
+        }
+        let mut families = Vec::new();
+        let mut assigned = Vec::new();
+        for (name, patterns) in &self.periph_families {
+            let members = instances
+                .values()
+                .flatten()
+                .filter(|instance| patterns.iter().any(|pattern| glob_match(pattern, instance)))
+                .filter(|instance| !assigned.contains(*instance))
+                .cloned()
+                .collect::<Vec<_>>();
+            if members.is_empty() {
+                bail!("peripheral family `{name}` doesn't match any peripheral");
+            }
+            assigned.extend(members.iter().cloned());
+            families.push(((*name).to_owned(), members));
+        }
+        let mut groups = device.identical_periphs()?;
+        for peripherals in device.periph_groups()?.into_values() {
+            groups.push(
+                peripherals
+                    .into_iter()
+                    .filter_map(|peripheral| instances.get(peripheral.name.as_str()))
+                    .flatten()
+                    .cloned()
+                    .collect(),
+            );
+        }
+        for (name, names) in &instances {
+            let peripheral = &device.peripherals[*name];
+            if peripheral.dim.is_some() {
+                groups.push(names.clone());
+            } else if let Some(parent) = &peripheral.derived_from {
+                groups.push(vec![parent.clone(), peripheral.name.clone()]);
+            }
+        }
+        let order = instances.values().flatten().collect::<Vec<_>>();
+        for mut members in merge_groups(groups) {
+            members.retain(|member| order.contains(&member) && !assigned.contains(member));
+            members.sort_by_key(|member| order.iter().position(|name| *name == member));
+            if members.len() > 1 {
+                let mut prefix = members[0].as_str();
+                for member in &members[1..] {
+                    let len =
+                        prefix.chars().zip(member.chars()).take_while(|(a, b)| a == b).count();
+                    prefix = &prefix[..len];
+                }
+                if members
+                    .iter()
+                    .any(|member| member[prefix.len()..].starts_with(|c: char| c.is_ascii_digit()))
+                {
+                    prefix = prefix.trim_end_matches(|c: char| c.is_ascii_digit());
+                }
+                let prefix = prefix.trim_end_matches('_');
+                let name = if prefix.is_empty() { members[0].clone() } else { prefix.to_owned() };
+                families.push((name, members));
+            }
+        }
+        for (i, (name, members)) in families.iter().enumerate() {
+            if let Some((other, other_members)) =
+                families[..i].iter().find(|(other, _)| camel_case(other) == camel_case(name))
+            {
+                bail!(
+                    "peripheral families `{other}` ({}) and `{name}` ({}) have the same name",
+                    other_members.join(", "),
+                    members.join(", ")
+                );
+            }
+        }
+        Ok(families)
+    }
+}
+
+fn merge_groups(groups: Vec<Vec<String>>) -> Vec<Vec<String>> {
+    let mut merged = Vec::<Vec<String>>::new();
+    for group in groups {
+        let (overlapping, rest): (Vec<_>, Vec<_>) =
+            merged.into_iter().partition(|other| other.iter().any(|name| group.contains(name)));
+        merged = rest;
+        let mut union = overlapping.concat();
+        for name in group {
+            if !union.contains(&name) {
+                union.push(name);
+            }
+        }
+        merged.push(union);
+    }
+    merged
+}
+
+fn collect_registers<'a>(members: &[&'a ResolvedPeripheral]) -> Registers<'a> {
+    let mut registers = Registers::new();
+    let mut mismatched_registers = HashSet::new();
+    let mut mismatched_fields = HashSet::new();
+    for member in members {
+        for register in &member.registers {
+            let path = register.path.as_slice();
+            let (generic, count, fields) =
+                registers.entry(path).or_insert((register, 0, Fields::new()));
+            if (generic.size, generic.access) != (register.size, register.access) {
+                mismatched_registers.insert(path);
+            }
+            *count += 1;
+            for field in &register.fields {
+                let (width, access, count) =
+                    fields.entry(field.name.as_str()).or_insert((field.bit_width, field.access, 0));
+                if (*width, *access) != (field.bit_width, field.access) {
+                    mismatched_fields.insert((path, field.name.as_str()));
+                }
+                *count += 1;
+            }
+        }
+    }
+    registers.retain(|path, _| !mismatched_registers.contains(path));
+    for (path, (_, _, fields)) in &mut registers {
+        fields.retain(|name, _| !mismatched_fields.contains(&(*path, *name)));
+    }
+    registers
+}
+
+fn generate_periph(
+    output: &mut impl Write,
+    name: &str,
+    members: usize,
+    registers: &Registers<'_>,
+) -> Result<()> {
+    let trait_name = camel_case(name);
+    writeln!(output, "periph! {{")?;
+    writeln!(output, "    /// Generic {name} peripheral variant.")?;
+    writeln!(output, "    pub trait {trait_name}Map {{}}")?;
+    writeln!(output)?;
+    writeln!(output, "    /// Generic {name} peripheral.")?;
+    writeln!(output, "    pub struct {trait_name}Periph;")?;
+    writeln!(output)?;
+    writeln!(output, "    {} {{", sanitize(name))?;
+    for (path, (register, count, fields)) in registers {
+        let register_access = access_prefix(register.access);
+        writeln!(output, "        {} {{", sanitize_path(path))?;
+        write!(output, "            0x{:02X} {register_access}Reg", register.size)?;
+        if *count < members {
+            write!(output, " Option")?;
+        }
+        writeln!(output, ";")?;
+        for (name, (width, access, field_count)) in fields {
+            let bits = if *width == 1 { "Bit" } else { "Bits" };
+            write!(
+                output,
+                "            {} {{ {register_access}{}RegField{bits}",
+                sanitize(name),
+                access_prefix(*access)
+            )?;
+            if field_count < count {
+                write!(output, " Option")?;
+            }
+            writeln!(output, " }}")?;
+        }
+        writeln!(output, "        }}")?;
+    }
+    writeln!(output, "    }}")?;
+    writeln!(output, "}}")?;
+    Ok(())
+}
+
+fn generate_periph_map(
+    output: &mut impl Write,
+    name: &str,
+    member: &ResolvedPeripheral,
+    registers: &Registers<'_>,
+    naming: &Naming<'_>,
+    reg_path: &str,
+    periph_path: &str,
+) -> Result<()> {
+    let member_name = naming.peripheral(&member.name);
+    let struct_name = camel_case(&member_name);
+    writeln!(output, "periph::map! {{")?;
+    writeln!(output, "    /// Extracts {} register tokens.", member.name)?;
+    writeln!(output, "    pub macro periph_{};", member_name.to_lowercase())?;
+    writeln!(output)?;
+    writeln!(output, "    /// {} peripheral variant.", member.name)?;
+    writeln!(output, "    pub struct {struct_name};")?;
+    writeln!(output)?;
+    writeln!(output, "    impl {}Map for {struct_name} {{}}", camel_case(name))?;
+    writeln!(output)?;
+    writeln!(output, "    {reg_path};")?;
+    writeln!(output, "    {periph_path};")?;
+    writeln!(output)?;
+    writeln!(output, "    {} {{", sanitize(name))?;
+    writeln!(output, "        {member_name};")?;
+    for (path, (_, _, fields)) in registers {
+        let generic = sanitize_path(path);
+        let Some(register) = member.registers.iter().find(|register| register.path == *path) else {
+            writeln!(output, "        {generic};")?;
+            continue;
+        };
+        writeln!(output, "        {generic} {{")?;
+        writeln!(output, "            {};", naming.register(&member.name, path))?;
+        for name in fields.keys() {
+            if register.field(name).is_some() {
+                let field_name = naming.field(&member.name, path, name);
+                writeln!(output, "            {} {{ {field_name} }}", sanitize(name))?;
+            } else {
+                writeln!(output, "            {};", sanitize(name))?;
+            }
+        }
+        writeln!(output, "        }}")?;
+    }
+    writeln!(output, "    }}")?;
+    writeln!(output, "}}")?;
+    Ok(())
+}
+
+fn access_prefix(access: Access) -> &'static str {
+    match access {
+        Access::ReadOnly => "Ro",
+        Access::WriteOnly => "Wo",
+        Access::ReadWrite | Access::ReadWriteonce => "Rw",
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, Generator};
+
+    fn element(tag: &str, name: &str, content: &str) -> String {
+        format!("<{tag}><name>{name}</name><description>{name}</description>{content}</{tag}>")
+    }
+
+    fn peripheral(name: &str, group: &str, registers: &str) -> String {
+        let content = format!(
+            r"<groupName>{group}</groupName><baseAddress>0</baseAddress>
+                <registers>{registers}</registers>"
+        );
+        element("peripheral", name, &content)
+    }
+
+    fn register(name: &str, offset: u32, size: u32, fields: &[(&str, u32)]) -> String {
+        let mut content = format!("<addressOffset>{offset}</addressOffset><size>{size}</size>");
+        if !fields.is_empty() {
+            content.push_str("<fields>");
+            for (name, width) in fields {
+                let bits = format!("<bitOffset>0</bitOffset><bitWidth>{width}</bitWidth>");
+                content.push_str(&element("field", name, &bits));
+            }
+            content.push_str("</fields>");
+        }
+        element("register", name, &content)
+    }
+
+    fn periph_maps(generator: &Generator<'_>, peripherals: &[String]) -> eyre::Result<String> {
+        let device: Device = quick_xml::de::from_str(&format!(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue>
+                <peripherals>{}</peripherals>
+            </device>",
+            peripherals.concat()
+        ))?;
+        let device = generator.prepare(device)?;
+        let mut output = Vec::new();
+        generator.generate_periph_maps(&mut output, &device, "crate::reg", "crate::periph")?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn family_names() {
+        let generator = Generator::new("tokens");
+        let cr = register("CR", 0, 32, &[("EN", 1)]);
+        let output = periph_maps(&generator, &[
+            peripheral("TIM10", "TIM", &cr),
+            peripheral("TIM11", "TIM", &cr),
+        ])
+        .unwrap();
+        assert!(output.contains("pub trait TimMap {}"));
+        assert!(output.contains("    TIM {\n"));
+        let output = periph_maps(&generator, &[
+            peripheral("TIM1", "TIM", &cr),
+            peripheral("TIM1_ALT", "TIM", &cr),
+        ])
+        .unwrap();
+        assert!(output.contains("pub trait Tim1Map {}"));
+        let mut generator = Generator::new("tokens");
+        generator.periph_family("TIMER", &["TIM*"]);
+        let output = periph_maps(&generator, &[
+            peripheral("TIM10", "TIM", &cr),
+            peripheral("TIM11", "TIM", &cr),
+        ])
+        .unwrap();
+        assert!(output.contains("pub trait TimerMap {}"));
+    }
+
+    #[test]
+    fn family_name_collision() {
+        let generator = Generator::new("tokens");
+        let narrow = register("CR", 0, 16, &[]);
+        let wide = register("CR", 0, 32, &[]);
+        let error = periph_maps(&generator, &[
+            peripheral("USART1", "A", &narrow),
+            peripheral("USART2", "B", &wide),
+            peripheral("USART3", "B", &wide),
+            peripheral("USART6", "A", &narrow),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "peripheral families `USART` (USART1, USART6) and `USART` (USART2, USART3) have the \
+             same name"
+        );
+    }
+
+    #[test]
+    fn optional_registers_and_fields() {
+        let generator = Generator::new("tokens");
+        let output = periph_maps(&generator, &[
+            peripheral(
+                "SPI1",
+                "SPI",
+                &[register("CR", 0, 32, &[("EN", 1), ("CRC", 1)]), register("DR", 4, 32, &[])]
+                    .concat(),
+            ),
+            peripheral("SPI2", "SPI", &register("CR", 0, 32, &[("EN", 1)])),
+        ])
+        .unwrap();
+        assert!(output.contains(
+            "        CR {\n            0x20 RwReg;\n            EN { RwRwRegFieldBit }\n            \
+             CRC { RwRwRegFieldBit Option }\n        }\n        DR {\n            0x20 RwReg \
+             Option;\n        }\n"
+        ));
+        assert!(output.contains("        DR;\n"));
+        assert!(output.contains("            CRC;\n"));
+    }
+
+    #[test]
+    fn mismatched_registers_and_fields() {
+        let generator = Generator::new("tokens");
+        let output = periph_maps(&generator, &[
+            peripheral(
+                "ADC1",
+                "ADC",
+                &[register("CR", 0, 32, &[("EN", 1), ("MODE", 2)]), register("DR", 4, 16, &[])]
+                    .concat(),
+            ),
+            peripheral(
+                "ADC2",
+                "ADC",
+                &[register("CR", 0, 32, &[("EN", 1), ("MODE", 3)]), register("DR", 4, 32, &[])]
+                    .concat(),
+            ),
+        ])
+        .unwrap();
+        assert!(output.contains(
+            "    ADC {\n        CR {\n            0x20 RwReg;\n            EN { RwRwRegFieldBit \
+             }\n        }\n    }\n"
+        ));
+        assert!(!output.contains("MODE"));
+        assert!(!output.contains("DR"));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::device::Cluster;
+use crate::traverse::{dim_name, traverse_peripheral_registers};
+use crate::{
+    Access, Device, EnumeratedValues, Field, Peripheral, Protection, Register, WriteConstraint,
+};
+use eyre::{eyre, Result};
+use std::ops::{Range, RangeInclusive};
+
+/// Flattened view of a device with all inheritance and arrays resolved.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ResolvedDevice {
+    /// The string identifies the device or device series.
+    pub name: String,
+    /// Resolved peripheral instances in the order of the description.
+    pub peripherals: Vec<ResolvedPeripheral>,
+}
+
+/// Peripheral instance with all inheritance and arrays resolved.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ResolvedPeripheral {
+    /// The peripheral instance name.
+    pub name: String,
+    /// The string provides an overview of the purpose and functionality of the
+    /// peripheral.
+    pub description: Option<String>,
+    /// The name of the peripheral which memory block this peripheral
+    /// redefines.
+    pub alternate_peripheral: Option<String>,
+    /// Lowest address reserved or used by the peripheral instance.
+    pub base_address: u32,
+    /// Resolved register instances ordered by address.
+    pub registers: Vec<ResolvedRegister>,
+}
+
+/// Register instance with all inheritance and arrays resolved.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ResolvedRegister {
+    /// The name of the containing peripheral instance.
+    pub peripheral_name: String,
+    /// Cluster instance names followed by the register instance name.
+    pub path: Vec<String>,
+    /// Descriptions of the containing clusters followed by the description of
+    /// the register.
+    pub description: Vec<String>,
+    /// The name of the register which memory location this register
+    /// redefines.
+    pub alternate_register: Option<String>,
+    /// Whether the register provides an alternative description of a memory
+    /// location through `alternatePeripheral`, `alternateCluster`, or
+    /// `alternateRegister`.
+    pub alternate: bool,
+    /// The absolute address of the register.
+    pub address: u32,
+    /// The bit-width of the register.
+    pub size: u32,
+    /// The value of the register at RESET.
+    pub reset_value: u32,
+    /// The access rights for the register.
+    pub access: Access,
+    /// The protection level for the register, if specified.
+    pub protection: Option<Protection>,
+    /// Resolved field instances.
+    pub fields: Vec<ResolvedField>,
+}
+
+/// Field instance with all inheritance and arrays resolved.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct ResolvedField {
+    /// The field instance name.
+    pub name: String,
+    /// String describing the details of the field.
+    pub description: String,
+    /// The position of the least significant bit of the field within the
+    /// register.
+    pub bit_offset: u32,
+    /// The bit-width of the field within the register.
+    pub bit_width: u32,
+    /// The access rights for the field.
+    pub access: Access,
+    /// Constraints for writing values to the field.
+    pub write_constraint: Option<WriteConstraint>,
+    /// Sets of named values of the field.
+    pub enumerated_values: Vec<EnumeratedValues>,
+    /// Force the field to have multiple bits interface.
+    pub force_bits: bool,
+}
+
+impl Device {
+    /// Resolves the device into a flattened model.
+    ///
+    /// Derived peripherals are merged with their parents, and peripheral,
+    /// cluster, register, and field arrays are expanded into separate
+    /// instances. Missing register properties are inherited from the enclosing
+    /// elements.
+    pub fn resolve(&self) -> Result<ResolvedDevice> {
+        let mut peripherals = Vec::new();
+        for peripheral in self.peripherals.values() {
+            resolve_peripheral(self, peripheral, &mut peripherals)?;
+        }
+        Ok(ResolvedDevice { name: self.name.clone(), peripherals })
+    }
+}
+
+impl ResolvedDevice {
+    /// Returns the peripheral instance with name `name`.
+    pub fn periph(&self, name: &str) -> Option<&ResolvedPeripheral> {
+        self.peripherals.iter().find(|peripheral| peripheral.name == name)
+    }
+
+    /// Returns the register instance at the path `path` in the
+    /// `PERIPHERAL/CLUSTER/REGISTER` form.
+    pub fn reg(&self, path: &str) -> Option<&ResolvedRegister> {
+        let (peripheral, path) = path.split_once('/')?;
+        self.periph(peripheral)?.reg(path)
+    }
+
+    /// Returns an iterator over all register instances of the device.
+    pub fn regs(&self) -> impl Iterator<Item = &ResolvedRegister> + '_ {
+        self.peripherals.iter().flat_map(|peripheral| peripheral.registers.iter())
+    }
+}
+
+impl ResolvedPeripheral {
+    /// Returns the register instance at the path `path`.
+    pub fn reg(&self, path: &str) -> Option<&ResolvedRegister> {
+        self.registers
+            .iter()
+            .find(|register| register.path.iter().map(String::as_str).eq(path.split('/')))
+    }
+}
+
+impl ResolvedRegister {
+    /// Returns the field instance with name `name`.
+    pub fn field(&self, name: &str) -> Option<&ResolvedField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Returns the range of bytes occupied by the register.
+    pub fn address_range(&self) -> Range<u64> {
+        let start = u64::from(self.address);
+        start..start + u64::from((self.size / 8 + u32::from(self.size % 8 != 0)).max(1))
+    }
+
+    /// Returns the register path in the `PERIPHERAL/CLUSTER/REGISTER` form.
+    pub fn full_path(&self) -> String {
+        let mut path = self.peripheral_name.clone();
+        for name in &self.path {
+            path.push('/');
+            path.push_str(name);
+        }
+        path
+    }
+}
+
+impl ResolvedField {
+    /// Returns the range of bits occupied by the field within the register.
+    ///
+    /// Saturates for malformed fields instead of overflowing. A zero-width
+    /// field yields the single bit at its offset.
+    pub fn bit_range(&self) -> RangeInclusive<u32> {
+        self.bit_offset..=self.bit_offset.saturating_add(self.bit_width.max(1) - 1)
+    }
+
+    /// Returns the mask of bits occupied by the field within the register.
+    /// Bits past bit 31 are ignored.
+    pub fn mask(&self) -> u32 {
+        if self.bit_width == 0 {
+            return 0;
+        }
+        u32::MAX
+            .checked_shr(32_u32.saturating_sub(self.bit_width))
+            .unwrap_or(0)
+            .checked_shl(self.bit_offset)
+            .unwrap_or(0)
+    }
+}
+
+fn resolve_peripheral(
+    device: &Device,
+    peripheral: &Peripheral,
+    peripherals: &mut Vec<ResolvedPeripheral>,
+) -> Result<()> {
+    let parent = peripheral.derived_from(device)?;
+    let mut instances = (0..peripheral.dim.unwrap_or(1))
+        .map(|peripheral_n| {
+            let name = dim_name(peripheral_n, &peripheral.name);
+            let base_address = peripheral_n
+                .checked_mul(peripheral.dim_increment.unwrap_or(0))
+                .and_then(|offset| peripheral.base_address.checked_add(offset))
+                .ok_or_else(|| eyre!("peripheral `{name}` address overflows"))?;
+            Ok(ResolvedPeripheral {
+                name,
+                description: peripheral.description(parent).map(ToOwned::to_owned),
+                alternate_peripheral: peripheral.alternate_peripheral.clone(),
+                base_address,
+                registers: Vec::new(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    traverse_peripheral_registers(peripheral, parent, |clusters, register| {
+        let size = register.size(device, peripheral, parent)?;
+        let reset_value = register.reset_value(device, peripheral, parent)?;
+        let access = register.access(device, peripheral, parent).unwrap_or(Access::ReadWrite);
+        let protection = register.protection(device, peripheral, parent, &clusters);
+        let mut description =
+            clusters.iter().map(|cluster| cluster.description.clone()).collect::<Vec<_>>();
+        description.push(register.description.clone());
+        let mut fields = Vec::new();
+        for field in &register.fields {
+            resolve_field(field, access, &mut fields)?;
+        }
+        let alternate = peripheral.alternate_peripheral.is_some()
+            || clusters.iter().any(|cluster| cluster.alternate_cluster.is_some())
+            || register.alternate_register.is_some();
+        for (path, offset) in expand_register(&clusters, register)? {
+            for instance in &mut instances {
+                let address = instance.base_address.checked_add(offset).ok_or_else(|| {
+                    eyre!("register `{}/{}` address overflows", instance.name, path.join("/"))
+                })?;
+                instance.registers.push(ResolvedRegister {
+                    peripheral_name: instance.name.clone(),
+                    path: path.clone(),
+                    description: description.clone(),
+                    alternate_register: register.alternate_register.clone(),
+                    alternate,
+                    address,
+                    size,
+                    reset_value,
+                    access,
+                    protection,
+                    fields: fields.clone(),
+                });
+            }
+        }
+        Ok(())
+    })?;
+    for instance in &mut instances {
+        instance.registers.sort_by_key(|register| register.address);
+    }
+    peripherals.extend(instances);
+    Ok(())
+}
+
+fn expand_register(clusters: &[&Cluster], register: &Register) -> Result<Vec<(Vec<String>, u32)>> {
+    fn element_offset(
+        offset: u32,
+        address_offset: u32,
+        number: u32,
+        dim_increment: Option<u32>,
+        path: &[String],
+    ) -> Result<u32> {
+        number
+            .checked_mul(dim_increment.unwrap_or(0))
+            .and_then(|increment| increment.checked_add(address_offset))
+            .and_then(|increment| increment.checked_add(offset))
+            .ok_or_else(|| eyre!("`{}` address offset overflows", path.join("/")))
+    }
+    let mut instances = vec![(Vec::new(), 0)];
+    for cluster in clusters {
+        let mut expanded = Vec::new();
+        for (path, offset) in instances {
+            for cluster_n in 0..cluster.dim.unwrap_or(1) {
+                let mut path = path.clone();
+                path.push(dim_name(cluster_n, &cluster.name));
+                let offset = element_offset(
+                    offset,
+                    cluster.address_offset,
+                    cluster_n,
+                    cluster.dim_increment,
+                    &path,
+                )?;
+                expanded.push((path, offset));
+            }
+        }
+        instances = expanded;
+    }
+    let mut expanded = Vec::new();
+    for (path, offset) in instances {
+        for register_n in 0..register.dim.unwrap_or(1) {
+            let mut path = path.clone();
+            path.push(dim_name(register_n, &register.name));
+            let offset = element_offset(
+                offset,
+                register.address_offset,
+                register_n,
+                register.dim_increment,
+                &path,
+            )?;
+            expanded.push((path, offset));
+        }
+    }
+    Ok(expanded)
+}
+
+fn resolve_field(
+    field: &Field,
+    base_access: Access,
+    fields: &mut Vec<ResolvedField>,
+) -> Result<()> {
+    let (bit_offset, bit_width) = field.try_bit_range()?;
+    for field_n in 0..field.dim.unwrap_or(1) {
+        let name = dim_name(field_n, &field.name);
+        let bit_offset = field_n
+            .checked_mul(field.dim_increment.unwrap_or(0))
+            .and_then(|increment| increment.checked_add(bit_offset))
+            .ok_or_else(|| eyre!("field `{name}` bit offset overflows"))?;
+        fields.push(ResolvedField {
+            name,
+            description: field.description.clone(),
+            bit_offset,
+            bit_width,
+            access: field.access.unwrap_or(base_access),
+            write_constraint: field.write_constraint.clone(),
+            enumerated_values: field.enumerated_values.clone(),
+            force_bits: field.force_bits,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use crate::device::{block_protection, RegisterTree};
+use crate::generator::SecureAliasCallback;
+use crate::{Device, Protection};
+use eyre::{bail, Result};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+
+/// Adds a secure alias named `{prefix}{name}` for each peripheral for which
+/// `secure_alias_callback` returns the secure base address.
+///
+/// Registers protected as secure are removed from the original peripheral,
+/// and registers protected as non-secure are removed from the alias.
+pub(crate) fn add_secure_aliases(
+    device: &mut Device,
+    prefix: &str,
+    secure_alias_callback: &dyn SecureAliasCallback,
+    exclude_peripherals: &[&str],
+) -> Result<()> {
+    let aliases = device
+        .peripherals
+        .values()
+        .filter(|peripheral| !exclude_peripherals.contains(&peripheral.name.as_str()))
+        .filter_map(|peripheral| {
+            secure_alias_callback(peripheral.name.clone(), peripheral.base_address)
+                .map(|base_address| (peripheral.name.clone(), base_address))
+        })
+        .collect::<Vec<_>>();
+    let names = aliases.iter().map(|(name, _)| name.clone()).collect::<HashSet<_>>();
+    for (name, _) in &aliases {
+        let children = device
+            .peripherals
+            .values()
+            .filter(|peripheral| peripheral.derived_from.as_ref() == Some(name))
+            .map(|peripheral| peripheral.name.clone())
+            .collect::<Vec<_>>();
+        for child in children {
+            detach(device, &child)?;
+        }
+        detach(device, name)?;
+    }
+    for (name, base_address) in aliases {
+        let alias_name = format!("{prefix}{name}");
+        if device.peripherals.contains_key(&alias_name) {
+            bail!("peripheral `{alias_name}` already exists");
+        }
+        let peripheral = &device.peripherals[&name];
+        let protection = peripheral.protection.or(device.protection);
+        let mut alias = peripheral.clone();
+        alias.name.clone_from(&alias_name);
+        alias.base_address = base_address;
+        alias.alternate_peripheral = alias
+            .alternate_peripheral
+            .filter(|name| names.contains(name))
+            .map(|name| format!("{prefix}{name}"));
+        let blocks = alias.address_blocks.clone();
+        let default = |offset| block_protection(&blocks, offset).or(protection);
+        let peripheral = device.peripherals.get_mut(&name).unwrap();
+        retain_protection(&mut peripheral.registers, 0, None, &default, Protection::Secure);
+        if protection != Some(Protection::NonSecure) {
+            retain_protection(&mut alias.registers, 0, None, &default, Protection::NonSecure);
+            device.peripherals.insert(alias_name, alias);
+        }
+    }
+    Ok(())
+}
+
+/// Copies registers and default properties of the parent peripheral into the
+/// peripheral `name`, and removes its `derived_from`.
+fn detach(device: &mut Device, name: &str) -> Result<()> {
+    let peripheral = &device.peripherals[name];
+    let Some(parent) = peripheral.derived_from(device)? else { return Ok(()) };
+    let parent = parent.clone();
+    let peripheral = device.peripherals.get_mut(name).unwrap();
+    for (key, node) in parent.registers {
+        peripheral.registers.entry(key).or_insert(node);
+    }
+    peripheral.description = peripheral.description.take().or(parent.description);
+    peripheral.group_name = peripheral.group_name.take().or(parent.group_name);
+    peripheral.size = peripheral.size.or(parent.size);
+    peripheral.reset_value = peripheral.reset_value.or(parent.reset_value);
+    peripheral.access = peripheral.access.or(parent.access);
+    peripheral.protection = peripheral.protection.or(parent.protection);
+    if peripheral.address_blocks.is_empty() {
+        peripheral.address_blocks = parent.address_blocks;
+    }
+    peripheral.derived_from = None;
+    Ok(())
+}
+
+/// Removes registers with the resolved protection level `removed`, and
+/// alternate references to them.
+///
+/// `default` resolves the protection level of a register at the given offset
+/// not covered by the register or its clusters.
+fn retain_protection(
+    tree: &mut IndexMap<String, RegisterTree>,
+    offset: u32,
+    protection: Option<Protection>,
+    default: &dyn Fn(u32) -> Option<Protection>,
+    removed: Protection,
+) {
+    tree.retain(|_, node| match node {
+        RegisterTree::Register(register) => {
+            register.protection.or(protection).or_else(|| default(offset + register.address_offset))
+                != Some(removed)
+        }
+        RegisterTree::Cluster(cluster) => {
+            retain_protection(
+                &mut cluster.register,
+                offset + cluster.address_offset,
+                cluster.protection.or(protection),
+                default,
+                removed,
+            );
+            true
+        }
+    });
+    let keys = tree.keys().cloned().collect::<HashSet<_>>();
+    for node in tree.values_mut() {
+        match node {
+            RegisterTree::Register(register) => {
+                register.alternate_register =
+                    register.alternate_register.take().filter(|name| keys.contains(name));
+            }
+            RegisterTree::Cluster(cluster) => {
+                cluster.alternate_cluster =
+                    cluster.alternate_cluster.take().filter(|name| keys.contains(name));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::add_secure_aliases;
+    use crate::Device;
+    use crate::device::RegisterTree;
+
+    fn device() -> Device {
+        quick_xml::de::from_str(
+            r#"<device><name>TEST</name><size>32</size><resetValue>0</resetValue><peripherals>
+                <peripheral><name>TIM1</name><description>t</description>
+                    <baseAddress>0x40000000</baseAddress>
+                    <registers>
+                        <register><name>SEC</name><description>r</description>
+                            <addressOffset>0</addressOffset>
+                            <protection>s</protection></register>
+                        <register><name>ALT</name><description>r</description>
+                            <alternateRegister>SEC</alternateRegister>
+                            <addressOffset>0</addressOffset></register>
+                        <register><name>NSEC</name><description>r</description>
+                            <addressOffset>4</addressOffset>
+                            <protection>n</protection></register>
+                        <register><name>PRIV</name><description>r</description>
+                            <addressOffset>8</addressOffset>
+                            <protection>p</protection></register>
+                        <register><name>ANY</name><description>r</description>
+                            <addressOffset>12</addressOffset></register>
+                    </registers>
+                </peripheral>
+                <peripheral derivedFrom="TIM1"><name>TIM2</name>
+                    <baseAddress>0x40000400</baseAddress></peripheral>
+                <peripheral><name>UART1</name><description>u</description>
+                    <baseAddress>0x40001000</baseAddress>
+                    <protection>n</protection>
+                    <registers>
+                        <register><name>DR</name><description>r</description>
+                            <addressOffset>0</addressOffset></register>
+                    </registers>
+                </peripheral>
+            </peripherals></device>"#,
+        )
+        .unwrap()
+    }
+
+    fn registers(device: &Device, peripheral: &str) -> Vec<(String, Option<String>)> {
+        device.peripherals[peripheral]
+            .registers
+            .values()
+            .map(|node| match node {
+                RegisterTree::Register(register) => {
+                    (register.name.clone(), register.alternate_register.clone())
+                }
+                RegisterTree::Cluster(cluster) => (cluster.name.clone(), None),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn secure_aliases() {
+        let mut device = device();
+        add_secure_aliases(
+            &mut device,
+            "S_",
+            &|name: String, base_address| (name != "TIM2").then_some(base_address + 0x1000_0000),
+            &[],
+        )
+        .unwrap();
+        let names = |names: &[(&str, Option<&str>)]| {
+            names
+                .iter()
+                .map(|&(name, alternate)| (name.to_owned(), alternate.map(str::to_owned)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            registers(&device, "TIM1"),
+            names(&[("ALT", None), ("NSEC", None), ("PRIV", None), ("ANY", None)])
+        );
+        assert_eq!(
+            registers(&device, "S_TIM1"),
+            names(&[("SEC", None), ("ALT", Some("SEC")), ("PRIV", None), ("ANY", None)])
+        );
+        assert_eq!(device.peripherals["S_TIM1"].base_address, 0x5000_0000);
+        let tim2 = &device.peripherals["TIM2"];
+        assert_eq!(tim2.derived_from, None);
+        assert_eq!(tim2.description.as_deref(), Some("t"));
+        assert_eq!(
+            registers(&device, "TIM2"),
+            names(&[
+                ("SEC", None),
+                ("ALT", Some("SEC")),
+                ("NSEC", None),
+                ("PRIV", None),
+                ("ANY", None)
+            ])
+        );
+        assert!(!device.peripherals.contains_key("S_TIM2"));
+        assert!(!device.peripherals.contains_key("S_UART1"));
+        assert_eq!(registers(&device, "UART1"), names(&[("DR", None)]));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::generator::{generate_peripheral, Generator, PreparedDevice};
+use eyre::Result;
+use indexmap::IndexMap;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Register token generated by [`Generator::generate_regs`].
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegToken {
+    /// The token name in the `PERIPHERAL_CLUSTER_REGISTER` form.
+    pub name: String,
+    /// The register address.
+    pub address: u32,
+    /// The register traits.
+    pub traits: Vec<String>,
+    /// Field names mapped to the field traits.
+    pub fields: IndexMap<String, Vec<String>>,
+}
+
+/// Changes of generated register tokens between two devices.
+#[non_exhaustive]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ApiReport {
+    /// The list of changes.
+    pub changes: Vec<ApiChange>,
+}
+
+/// A change of a generated register token.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiChange {
+    /// The token name in the `PERIPHERAL_CLUSTER_REGISTER` form.
+    pub token: String,
+    /// The kind of the change.
+    pub kind: ApiChangeKind,
+}
+
+/// Kind of a change of a generated register token.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ApiChangeKind {
+    /// The token was added.
+    Added,
+    /// The token was removed.
+    Removed,
+    /// The register address changed.
+    Moved {
+        /// The old address.
+        old: u32,
+        /// The new address.
+        new: u32,
+    },
+    /// The register traits changed.
+    Traits {
+        /// The old traits.
+        old: Vec<String>,
+        /// The new traits.
+        new: Vec<String>,
+    },
+    /// The field was added.
+    FieldAdded {
+        /// The field name.
+        field: String,
+    },
+    /// The field was removed.
+    FieldRemoved {
+        /// The field name.
+        field: String,
+    },
+    /// The field traits changed.
+    FieldTraits {
+        /// The field name.
+        field: String,
+        /// The old traits.
+        old: Vec<String>,
+        /// The new traits.
+        new: Vec<String>,
+    },
+}
+
+impl Generator<'_> {
+    /// Returns register tokens which [`Generator::generate_regs`] would
+    /// generate for `device`.
+    pub fn reg_tokens(&self, device: &PreparedDevice) -> Result<Vec<RegToken>> {
+        let device = self.prepared(device)?;
+        let mut tokens = Vec::new();
+        let mut generated = HashSet::new();
+        for peripheral in device.peripherals.values() {
+            if self.exclude_peripherals.iter().any(|&name| name == peripheral.name) {
+                continue;
+            }
+            generate_peripheral(device, peripheral, &mut generated, |mut instances| {
+                instances.retain(|(_, instance)| {
+                    self.filters.register(&instance.peripheral_name, &instance.name)
+                });
+                let traits = self.instance_traits(&instances);
+                for ((_, instance), traits) in instances.iter().zip(traits) {
+                    let fields = traits
+                        .fields
+                        .into_iter()
+                        .map(|(name, traits)| {
+                            (
+                                self.naming.field(&instance.peripheral_name, &instance.name, &name),
+                                traits,
+                            )
+                        })
+                        .collect();
+                    tokens.push(RegToken {
+                        name: format!(
+                            "{}_{}",
+                            self.naming.peripheral(&instance.peripheral_name),
+                            self.naming.register(&instance.peripheral_name, &instance.name)
+                        ),
+                        address: instance.address,
+                        traits: traits.register,
+                        fields,
+                    });
+                }
+                Ok(())
+            })?;
+        }
+        Ok(tokens)
+    }
+
+    /// Reports changes of generated register tokens between the `old` and
+    /// `new` revisions of a device.
+    pub fn reg_tokens_changes(
+        &self,
+        old: &PreparedDevice,
+        new: &PreparedDevice,
+    ) -> Result<ApiReport> {
+        let old = self.reg_tokens(old)?;
+        let new = self.reg_tokens(new)?;
+        let new = new.iter().map(|token| (token.name.as_str(), token)).collect::<IndexMap<_, _>>();
+        let mut report = ApiReport::default();
+        for old in &old {
+            let Some(new) = new.get(old.name.as_str()) else {
+                report.push(&old.name, ApiChangeKind::Removed);
+                continue;
+            };
+            if old.address != new.address {
+                report.push(&old.name, ApiChangeKind::Moved { old: old.address, new: new.address });
+            }
+            if old.traits != new.traits {
+                report.push(&old.name, ApiChangeKind::Traits {
+                    old: old.traits.clone(),
+                    new: new.traits.clone(),
+                });
+            }
+            for (field, old_traits) in &old.fields {
+                match new.fields.get(field) {
+                    Some(new_traits) if new_traits != old_traits => {
+                        report.push(&old.name, ApiChangeKind::FieldTraits {
+                            field: field.clone(),
+                            old: old_traits.clone(),
+                            new: new_traits.clone(),
+                        });
+                    }
+                    Some(_) => {}
+                    None => {
+                        report
+                            .push(&old.name, ApiChangeKind::FieldRemoved { field: field.clone() });
+                    }
+                }
+            }
+            for field in new.fields.keys().filter(|field| !old.fields.contains_key(*field)) {
+                report.push(&old.name, ApiChangeKind::FieldAdded { field: field.clone() });
+            }
+        }
+        let old = old.iter().map(|token| token.name.as_str()).collect::<HashSet<_>>();
+        for name in new.keys().filter(|name| !old.contains(*name)) {
+            report.push(name, ApiChangeKind::Added);
+        }
+        Ok(report)
+    }
+}
+
+impl ApiReport {
+    /// Returns `true` if there are no changes.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns an iterator over changes which may break code using the tokens.
+    pub fn breaking(&self) -> impl Iterator<Item = &ApiChange> + '_ {
+        self.changes.iter().filter(|change| change.kind.is_breaking())
+    }
+
+    fn push(&mut self, token: &str, kind: ApiChangeKind) {
+        self.changes.push(ApiChange { token: token.to_owned(), kind });
+    }
+}
+
+impl ApiChangeKind {
+    /// Returns `true` if the change may break code using the token.
+    pub fn is_breaking(&self) -> bool {
+        match self {
+            Self::Added | Self::Moved { .. } | Self::FieldAdded { .. } => false,
+            Self::Removed | Self::FieldRemoved { .. } => true,
+            Self::Traits { old, new } | Self::FieldTraits { old, new, .. } => {
+                old.iter().any(|name| !new.contains(name))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ApiReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ApiChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { token, kind } = self;
+        let mark = if kind.is_breaking() { '!' } else { ' ' };
+        match kind {
+            ApiChangeKind::Added => write!(f, "{mark} {token}: added"),
+            ApiChangeKind::Removed => write!(f, "{mark} {token}: removed"),
+            ApiChangeKind::Moved { old, new } => {
+                write!(f, "{mark} {token}: moved 0x{old:08X} -> 0x{new:08X}")
+            }
+            ApiChangeKind::Traits { old, new } => {
+                write!(f, "{mark} {token}: traits `{}` -> `{}`", old.join(" "), new.join(" "))
+            }
+            ApiChangeKind::FieldAdded { field } => write!(f, "{mark} {token}.{field}: added"),
+            ApiChangeKind::FieldRemoved { field } => write!(f, "{mark} {token}.{field}: removed"),
+            ApiChangeKind::FieldTraits { field, old, new } => write!(
+                f,
+                "{mark} {token}.{field}: traits `{}` -> `{}`",
+                old.join(" "),
+                new.join(" ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiChangeKind;
+    use crate::{Device, Generator};
+
+    fn device(registers: &str) -> Device {
+        quick_xml::de::from_str(&format!(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue>
+                <peripherals><peripheral><name>TIM1</name><description>Timer</description>
+                    <baseAddress>0x40000000</baseAddress>
+                    <registers>{registers}</registers>
+                </peripheral></peripherals>
+            </device>"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn changes() {
+        let generator = Generator::new("tokens");
+        let old = generator
+            .prepare(device(
+                r"<register><name>CR</name><description>Control</description>
+                    <addressOffset>0</addressOffset><access>read-write</access></register>
+                <register><name>SR</name><description>Status</description>
+                    <addressOffset>4</addressOffset></register>
+                <register><name>DR</name><description>Data</description>
+                    <addressOffset>8</addressOffset></register>",
+            ))
+            .unwrap();
+        let new = generator
+            .prepare(device(
+                r"<register><name>CR</name><description>Control</description>
+                    <addressOffset>0</addressOffset><access>read-only</access></register>
+                <register><name>SR</name><description>Status</description>
+                    <addressOffset>12</addressOffset></register>",
+            ))
+            .unwrap();
+        let report = generator.reg_tokens_changes(&old, &new).unwrap();
+        let changes = report
+            .changes
+            .iter()
+            .map(|change| (change.token.as_str(), &change.kind, change.kind.is_breaking()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [
+            (
+                "TIM1_CR",
+                &ApiChangeKind::Traits {
+                    old: vec!["RReg".into(), "WReg".into()],
+                    new: vec!["RReg".into(), "RoReg".into()],
+                },
+                true
+            ),
+            ("TIM1_SR", &ApiChangeKind::Moved { old: 0x4000_0004, new: 0x4000_000C }, false),
+            ("TIM1_DR", &ApiChangeKind::Removed, true),
+        ]);
+        assert_eq!(report.breaking().count(), 2);
+        assert!(generator.reg_tokens_changes(&old, &old).unwrap().is_empty());
+    }
+}
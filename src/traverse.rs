@@ -66,7 +66,7 @@ pub(crate) fn for_each_clusters_combination<T: Clone>(
             let mut init = init.clone();
             let mut k = n;
             for (j, (cluster, dim)) in variant.clusters.iter().zip(dim.iter()).enumerate() {
-                let cluster_n = (k % dim) as u32;
+                let cluster_n = k % dim;
                 if cluster_n >= matrix[j][i] {
                     continue 'outer;
                 }
@@ -79,3 +79,11 @@ pub(crate) fn for_each_clusters_combination<T: Clone>(
     }
     Ok(())
 }
+
+pub(crate) fn dim_name(number: u32, name: &str) -> String {
+    if let Some(name) = name.strip_suffix("[%s]") {
+        format!("{name}_{number}")
+    } else {
+        name.to_owned()
+    }
+}
@@ -0,0 +1,305 @@
+use crate::device::RegisterTree;
+use crate::{Device, Field, ResolvedDevice, ResolvedRegister};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A problem found in the description.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The severity of the problem.
+    pub severity: Severity,
+    /// The path of the element in the `PERIPHERAL/CLUSTER/REGISTER/FIELD` form.
+    pub path: String,
+    /// The problem description.
+    pub message: String,
+}
+
+/// Severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The description is suspicious, but can be used.
+    Warning,
+    /// The description is invalid.
+    Error,
+}
+
+impl Device {
+    /// Checks the description for common mistakes.
+    ///
+    /// Duplicate names are reported both for elements repeated in the
+    /// description, which are merged during parsing, and for names clashing
+    /// after array expansion.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Diagnostics::default();
+        for peripheral in self.peripherals.values() {
+            let parent = match peripheral.derived_from(self) {
+                Ok(parent) => parent,
+                Err(err) => {
+                    diagnostics.error(&peripheral.name, err);
+                    continue;
+                }
+            };
+            if peripheral.duplicates > 0 {
+                diagnostics.error(&peripheral.name, "duplicate peripheral name");
+            }
+            if peripheral.description(parent).map_or(true, str::is_empty) {
+                diagnostics.warning(&peripheral.name, "empty description");
+            }
+            validate_tree(&mut diagnostics, &peripheral.name, &peripheral.registers);
+        }
+        if diagnostics.fatal {
+            return diagnostics.list;
+        }
+        match self.resolve() {
+            Ok(device) => validate_resolved(&mut diagnostics, &device),
+            Err(err) => diagnostics.error(&self.name, err),
+        }
+        diagnostics.list
+    }
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, path: &str, message: impl fmt::Display) -> Self {
+        Self { severity, path: path.to_owned(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.path, self.message)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => write!(f, "warning"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Diagnostics {
+    list: Vec<Diagnostic>,
+    fatal: bool,
+}
+
+impl Diagnostics {
+    fn warning(&mut self, path: &str, message: impl fmt::Display) {
+        self.list.push(Diagnostic::new(Severity::Warning, path, message));
+    }
+
+    fn error(&mut self, path: &str, message: impl fmt::Display) {
+        self.list.push(Diagnostic::new(Severity::Error, path, message));
+    }
+}
+
+fn validate_tree(diagnostics: &mut Diagnostics, path: &str, tree: &IndexMap<String, RegisterTree>) {
+    for node in tree.values() {
+        if node.duplicates() > 0 {
+            let (kind, name) = match node {
+                RegisterTree::Register(register) => ("register", &register.name),
+                RegisterTree::Cluster(cluster) => ("cluster", &cluster.name),
+            };
+            diagnostics.error(&format!("{path}/{name}"), format!("duplicate {kind} name"));
+        }
+        match node {
+            RegisterTree::Register(register) => {
+                let path = format!("{path}/{}", register.name);
+                if register.description.is_empty() {
+                    diagnostics.warning(&path, "empty description");
+                }
+                if let Some(alternate_register) = &register.alternate_register {
+                    match tree.get(alternate_register) {
+                        Some(RegisterTree::Register(original)) => {
+                            if original.address_offset != register.address_offset {
+                                diagnostics.error(
+                                    &path,
+                                    format!(
+                                        "alternate register `{alternate_register}` is at offset \
+                                         0x{:X}, but this register is at offset 0x{:X}",
+                                        original.address_offset, register.address_offset
+                                    ),
+                                );
+                            }
+                        }
+                        _ => diagnostics.error(
+                            &path,
+                            format!("alternate register `{alternate_register}` not found"),
+                        ),
+                    }
+                }
+                for field in &register.fields {
+                    validate_field(diagnostics, &path, field);
+                }
+            }
+            RegisterTree::Cluster(cluster) => {
+                let path = format!("{path}/{}", cluster.name);
+                if cluster.description.is_empty() {
+                    diagnostics.warning(&path, "empty description");
+                }
+                validate_tree(diagnostics, &path, &cluster.register);
+            }
+        }
+    }
+}
+
+fn validate_field(diagnostics: &mut Diagnostics, path: &str, field: &Field) {
+    let path = format!("{path}/{}", field.name);
+    if field.description.is_empty() {
+        diagnostics.warning(&path, "empty description");
+    }
+    let reversed = match (field.lsb, field.msb, &field.bit_range) {
+        (Some(lsb), Some(msb), _) => msb < lsb,
+        (_, _, Some(bit_range)) => bit_range.end() < bit_range.start(),
+        _ => false,
+    };
+    if reversed {
+        diagnostics.error(&path, "msb is less than lsb");
+        diagnostics.fatal = true;
+    } else if field.bit_offset.or(field.lsb).is_none() && field.bit_range.is_none()
+        || field.bit_width.is_none() && field.msb.is_none() && field.bit_range.is_none()
+    {
+        diagnostics.error(&path, "missing bit range");
+        diagnostics.fatal = true;
+    } else if let Ok((_, width)) = field.try_bit_range() {
+        if width == 0 || width > 32 {
+            diagnostics.error(&path, format!("invalid bit width {width}"));
+            diagnostics.fatal = true;
+        }
+    }
+}
+
+fn validate_resolved(diagnostics: &mut Diagnostics, device: &ResolvedDevice) {
+    let mut peripherals = HashSet::new();
+    for peripheral in &device.peripherals {
+        if !peripherals.insert(&peripheral.name) {
+            diagnostics.error(&peripheral.name, "duplicate peripheral name");
+        }
+        let mut registers = HashSet::new();
+        for register in &peripheral.registers {
+            if !registers.insert(&register.path) {
+                diagnostics.error(&register.full_path(), "duplicate register name");
+            }
+            validate_register(diagnostics, register);
+        }
+    }
+    let mut registers = device
+        .regs()
+        .filter(|register| !register.alternate)
+        .map(|register| (register.address_range(), register))
+        .collect::<Vec<_>>();
+    registers.sort_by_key(|(range, _)| range.start);
+    for (i, (range, register)) in registers.iter().enumerate() {
+        for (_, other) in registers[i + 1..].iter().take_while(|(r, _)| r.start < range.end) {
+            diagnostics.warning(
+                &other.full_path(),
+                format!("overlaps with register `{}`", register.full_path()),
+            );
+        }
+    }
+}
+
+fn validate_register(diagnostics: &mut Diagnostics, register: &ResolvedRegister) {
+    let path = register.full_path();
+    let mut fields = HashSet::new();
+    let mut mask = 0;
+    for (i, field) in register.fields.iter().enumerate() {
+        let field_path = format!("{path}/{}", field.name);
+        if !fields.insert(&field.name) {
+            diagnostics.error(&field_path, "duplicate field name");
+        }
+        if u64::from(field.bit_offset) + u64::from(field.bit_width) > u64::from(register.size) {
+            diagnostics.error(
+                &field_path,
+                format!("extends past the register size of {} bits", register.size),
+            );
+        }
+        for other in &register.fields[..i] {
+            if other.mask() & field.mask() != 0 {
+                diagnostics.error(&field_path, format!("overlaps with field `{}`", other.name));
+            }
+        }
+        mask |= field.mask();
+    }
+    if !register.fields.is_empty() && register.reset_value & !mask != 0 {
+        diagnostics.warning(
+            &path,
+            format!("reset value 0x{:08X} has bits set outside of any field", register.reset_value),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Device, Severity};
+
+    fn errors(xml: &str) -> Vec<String> {
+        let device: Device = quick_xml::de::from_str(xml).unwrap();
+        device
+            .validate()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| format!("{}: {}", diagnostic.path, diagnostic.message))
+            .collect()
+    }
+
+    #[test]
+    fn invalid_bit_width() {
+        let errors = errors(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue><peripherals>
+                <peripheral><name>P</name><description>p</description><baseAddress>0</baseAddress>
+                    <registers>
+                        <register><name>R</name><description>r</description>
+                            <addressOffset>0</addressOffset>
+                            <fields>
+                                <field><name>WIDE</name><description>f</description>
+                                    <bitOffset>0</bitOffset><bitWidth>33</bitWidth></field>
+                                <field><name>EMPTY</name><description>f</description>
+                                    <bitOffset>0</bitOffset><bitWidth>0</bitWidth></field>
+                            </fields>
+                        </register>
+                    </registers>
+                </peripheral>
+            </peripherals></device>",
+        );
+        assert_eq!(errors, ["P/R/WIDE: invalid bit width 33", "P/R/EMPTY: invalid bit width 0"]);
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let errors = errors(
+            r"<device><name>TEST</name><size>32</size><resetValue>0</resetValue><peripherals>
+                <peripheral><name>P</name><description>p</description><baseAddress>0</baseAddress>
+                </peripheral>
+                <peripheral><name>Q</name><description>q</description><baseAddress>0x200</baseAddress>
+                    <registers>
+                        <register><name>R</name><description>r</description>
+                            <addressOffset>0</addressOffset></register>
+                        <register><name>R</name><description>r</description>
+                            <addressOffset>4</addressOffset></register>
+                    </registers>
+                </peripheral>
+                <peripheral><name>P</name><description>p</description><baseAddress>0x100</baseAddress>
+                    <registers>
+                        <register><name>R</name><description>r</description>
+                            <addressOffset>0</addressOffset>
+                            <fields>
+                                <field><name>F</name><description>f</description><bitRange>[0:0]</bitRange></field>
+                                <field><name>F</name><description>f</description><bitRange>[1:1]</bitRange></field>
+                            </fields>
+                        </register>
+                    </registers>
+                </peripheral>
+            </peripherals></device>",
+        );
+        assert_eq!(errors, [
+            "P: duplicate peripheral name",
+            "Q/R: duplicate register name",
+            "P/R/F: duplicate field name",
+        ]);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::{Access, EnumeratedValue, ResolvedField, ResolvedRegister};
+use eyre::{bail, eyre, Result};
+use std::ops::RangeInclusive;
+
+/// A field of a decoded register value.
+#[non_exhaustive]
+#[derive(Clone, Debug)]
+pub struct DecodedField<'a> {
+    /// The resolved field.
+    pub field: &'a ResolvedField,
+    /// The range of bits occupied by the field within the register.
+    pub bit_range: RangeInclusive<u32>,
+    /// The numeric value of the field.
+    pub value: u32,
+    /// The named value matching the field value.
+    pub enumerated_value: Option<&'a EnumeratedValue>,
+    /// Whether the field value differs from the value at RESET.
+    pub changed: bool,
+}
+
+/// A value assigned to a field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldValue<'a> {
+    /// A numeric value.
+    Number(u32),
+    /// A name of an enumerated value.
+    Name(&'a str),
+}
+
+impl ResolvedRegister {
+    /// Decodes the raw register value `value` into a per-field breakdown.
+    pub fn decode(&self, value: u32) -> Vec<DecodedField<'_>> {
+        self.fields
+            .iter()
+            .map(|field| {
+                let field_value = field.extract(value);
+                DecodedField {
+                    field,
+                    bit_range: field.bit_range(),
+                    value: field_value,
+                    enumerated_value: field
+                        .enumerated_values
+                        .iter()
+                        .filter(|values| values.is_readable())
+                        .find_map(|values| values.find(field_value)),
+                    changed: field_value != field.extract(self.reset_value),
+                }
+            })
+            .collect()
+    }
+
+    /// Encodes a raw register value by applying field assignments `fields` to
+    /// the current register value `value`.
+    ///
+    /// Pass the value last read from the register, or `reset_value` to start
+    /// from the value at RESET.
+    pub fn encode<'a, K, V>(
+        &self,
+        value: u32,
+        fields: impl IntoIterator<Item = (K, V)>,
+    ) -> Result<u32>
+    where
+        K: AsRef<str>,
+        V: Into<FieldValue<'a>>,
+    {
+        let read_value = value;
+        let mut value = value;
+        for (name, field_value) in fields {
+            let name = name.as_ref();
+            let field = self
+                .field(name)
+                .ok_or_else(|| eyre!("field `{name}` not found in `{}`", self.full_path()))?;
+            let field_value =
+                field.encode(field_value.into(), field.extract(read_value)).map_err(|err| {
+                    err.wrap_err(format!("invalid value for `{}/{name}`", self.full_path()))
+                })?;
+            value = field.insert(value, field_value);
+        }
+        Ok(value)
+    }
+}
+
+impl ResolvedField {
+    /// Inserts the field value `field_value` into the raw register value
+    /// `value`.
+    pub fn insert(&self, value: u32, field_value: u32) -> u32 {
+        let mask = self.mask();
+        value & !mask | field_value.checked_shl(self.bit_offset).unwrap_or(0) & mask
+    }
+
+    /// Validates the field value `field_value` for writing and returns its
+    /// numeric value. `read_value` is the current value of the field, used
+    /// for `writeAsRead` constraints.
+    pub fn encode(&self, field_value: FieldValue<'_>, read_value: u32) -> Result<u32> {
+        if self.access == Access::ReadOnly {
+            bail!("field is read-only");
+        }
+        let value = match field_value {
+            FieldValue::Number(value) => value,
+            FieldValue::Name(name) => self.encode_name(name)?,
+        };
+        if u64::from(value) >= 1_u64.checked_shl(self.bit_width).unwrap_or(u64::MAX) {
+            bail!("value {value} does not fit in {} bits", self.bit_width);
+        }
+        if let Some(write_constraint) = &self.write_constraint {
+            if write_constraint.write_as_read && value != read_value {
+                bail!("value {value} differs from the read value {read_value}");
+            }
+            if write_constraint.use_enumerated_values
+                && !self
+                    .enumerated_values
+                    .iter()
+                    .filter(|values| values.is_writable())
+                    .flat_map(|values| &values.values)
+                    .any(|v| v.value.map_or(false, |v| v.matches(value)))
+            {
+                bail!("value {value} is not listed in the enumerated values");
+            }
+            if let Some(range) = &write_constraint.range {
+                if value < range.minimum || value > range.maximum {
+                    bail!("value {value} is out of range {}..={}", range.minimum, range.maximum);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Returns the numeric value of the writable enumerated value `name`.
+    ///
+    /// A default value without an explicit value encodes as the lowest value
+    /// not listed in the enumeration.
+    fn encode_name(&self, name: &str) -> Result<u32> {
+        let values = self
+            .enumerated_values
+            .iter()
+            .filter(|values| values.is_writable())
+            .flat_map(|values| &values.values)
+            .collect::<Vec<_>>();
+        let enumerated_value = values
+            .iter()
+            .find(|value| value.name == name)
+            .ok_or_else(|| eyre!("enumerated value `{name}` not found"))?;
+        if let Some(value) = enumerated_value.value {
+            return Ok(value.value);
+        }
+        if !enumerated_value.is_default {
+            bail!("enumerated value `{name}` has no value");
+        }
+        let max = u32::MAX.checked_shr(32 - self.bit_width.min(32)).unwrap_or(0);
+        (0..=max)
+            .find(|&n| !values.iter().any(|v| v.value.map_or(false, |v| v.matches(n))))
+            .ok_or_else(|| eyre!("enumerated value `{name}` has no unlisted value"))
+    }
+
+    /// Extracts the field value from the raw register value `value`.
+    pub fn extract(&self, value: u32) -> u32 {
+        (value & self.mask()).checked_shr(self.bit_offset).unwrap_or(0)
+    }
+}
+
+impl From<u32> for FieldValue<'_> {
+    fn from(value: u32) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl<'a> From<&'a str> for FieldValue<'a> {
+    fn from(name: &'a str) -> Self {
+        Self::Name(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FieldValue;
+    use crate::{Device, ResolvedDevice};
+
+    fn device() -> ResolvedDevice {
+        let device: Device = quick_xml::de::from_str(
+            r"<device>
+                <name>TEST</name>
+                <size>32</size>
+                <peripherals>
+                    <peripheral>
+                        <name>UART</name>
+                        <baseAddress>0x40000000</baseAddress>
+                        <registers>
+                            <register>
+                                <name>CR</name>
+                                <addressOffset>0</addressOffset>
+                                <resetValue>0x50</resetValue>
+                                <fields>
+                                    <field>
+                                        <name>MODE</name>
+                                        <bitRange>[2:0]</bitRange>
+                                        <enumeratedValues>
+                                            <enumeratedValue><name>OFF</name><value>0</value></enumeratedValue>
+                                            <enumeratedValue><name>ON</name><value>#01x</value></enumeratedValue>
+                                            <enumeratedValue><name>OTHER</name><isDefault>true</isDefault></enumeratedValue>
+                                        </enumeratedValues>
+                                    </field>
+                                    <field>
+                                        <name>KEY</name>
+                                        <bitOffset>4</bitOffset>
+                                        <bitWidth>4</bitWidth>
+                                        <writeConstraint><writeAsRead>true</writeAsRead></writeConstraint>
+                                    </field>
+                                    <field>
+                                        <name>BUSY</name>
+                                        <bitOffset>8</bitOffset>
+                                        <bitWidth>1</bitWidth>
+                                        <access>read-only</access>
+                                    </field>
+                                </fields>
+                            </register>
+                        </registers>
+                    </peripheral>
+                </peripherals>
+            </device>",
+        )
+        .unwrap();
+        device.resolve().unwrap()
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let device = device();
+        let register = device.reg("UART/CR").unwrap();
+        for (name, expected) in [("OFF", 0), ("ON", 2), ("OTHER", 1)] {
+            let value = register.encode(register.reset_value, [("MODE", name)]).unwrap();
+            assert_eq!(value, 0x50 | expected);
+            let decoded = register.decode(value);
+            assert_eq!(decoded[0].value, expected);
+            assert_eq!(decoded[0].enumerated_value.map(|v| v.name.as_str()), Some(name));
+            assert_eq!(decoded[0].changed, expected != 0);
+            assert_eq!(decoded[1].value, 5);
+            assert!(!decoded[1].changed);
+        }
+        let value = register.encode(0x1FF, [("MODE", FieldValue::Number(3))]).unwrap();
+        assert_eq!(value, 0x1FB);
+        assert_eq!(register.decode(value)[0].enumerated_value.unwrap().name, "ON");
+    }
+
+    #[test]
+    fn encode_checks_write_as_read_against_current_value() {
+        let device = device();
+        let register = device.reg("UART/CR").unwrap();
+        assert!(register.encode(0x70, [("KEY", 7)]).is_ok());
+        assert!(register.encode(register.reset_value, [("KEY", 7)]).is_err());
+    }
+
+    #[test]
+    fn encode_rejects_invalid_assignments() {
+        let device = device();
+        let register = device.reg("UART/CR").unwrap();
+        assert!(register.encode(0, [("MODE", 8)]).is_err());
+        assert!(register.encode(0, [("MODE", "FAST")]).is_err());
+        assert!(register.encode(0, [("BUSY", 1)]).is_err());
+        assert!(register.encode(0, [("MISSING", 1)]).is_err());
+    }
+}
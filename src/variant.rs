@@ -18,9 +18,9 @@ impl<'a> Variant<'a> {
 }
 
 pub(crate) fn trace_variants(device: &mut Device, exclude_peripherals: &[&str]) -> Result<()> {
-    fn peripheral_variants<'a, 'b>(
+    fn peripheral_variants<'a>(
         device: &'a mut Device,
-        periheral_name: &'b str,
+        periheral_name: &str,
     ) -> Option<&'a mut Vec<String>> {
         device.peripherals.get_mut(periheral_name).map(|p| &mut p.variants)
     }
@@ -30,7 +30,7 @@ pub(crate) fn trace_variants(device: &mut Device, exclude_peripherals: &[&str])
         }
         let peripheral = device.peripherals.get_mut(&key).unwrap();
         trace_tree(&mut peripheral.registers)?;
-        if let Some(alternate_peripheral) = peripheral.alternate_peripheral.as_ref().cloned() {
+        if let Some(alternate_peripheral) = peripheral.alternate_peripheral.clone() {
             let variants = peripheral_variants(device, &alternate_peripheral)
                 .ok_or_else(|| eyre!("peripheral referenced in `alternatePeripheral` not found"))?
                 .clone();
@@ -47,7 +47,7 @@ pub(crate) fn collect_variants<'a>(
     device: &'a Device,
     peripheral: &'a Peripheral,
     parent: Option<&'a Peripheral>,
-    clusters: &'a [&'a Cluster],
+    clusters: &[&'a Cluster],
     register: &'a Register,
 ) -> Result<Vec<Variant<'a>>> {
     fn is_paths_equal(
@@ -60,10 +60,10 @@ pub(crate) fn collect_variants<'a>(
             == clusters_b.iter().map(|c| c.address_offset).sum::<u32>() + register_b.address_offset
     }
 
-    fn peripheral_get<'a, 'b>(
+    fn peripheral_get<'a>(
         peripheral: &'a Peripheral,
         parent: Option<&'a Peripheral>,
-        name: &'b str,
+        name: &str,
     ) -> Option<&'a RegisterTree> {
         peripheral.registers.get(name).or_else(|| parent.and_then(|p| p.registers.get(name)))
     }
@@ -119,16 +119,16 @@ pub(crate) fn collect_variants<'a>(
 }
 
 fn trace_tree(tree: &mut IndexMap<String, RegisterTree>) -> Result<()> {
-    fn cluster_variants<'a, 'b>(
+    fn cluster_variants<'a>(
         tree: &'a mut IndexMap<String, RegisterTree>,
-        cluster_name: &'b str,
+        cluster_name: &str,
     ) -> Option<&'a mut Vec<String>> {
         tree.get_mut(cluster_name).map(|c| &mut c.unwrap_cluster_mut().variants)
     }
     for key in tree.keys().cloned().collect::<Vec<_>>() {
         match tree.get_mut(&key).unwrap() {
             RegisterTree::Register(register) => {
-                if let Some(alternate_register) = register.alternate_register.as_ref().cloned() {
+                if let Some(alternate_register) = register.alternate_register.clone() {
                     tree.get_mut(&alternate_register)
                         .ok_or_else(|| {
                             eyre!("register referenced in `alternateRegister` not found")
@@ -140,7 +140,7 @@ fn trace_tree(tree: &mut IndexMap<String, RegisterTree>) -> Result<()> {
             }
             RegisterTree::Cluster(cluster) => {
                 trace_tree(&mut cluster.register)?;
-                if let Some(alternate_cluster) = cluster.alternate_cluster.as_ref().cloned() {
+                if let Some(alternate_cluster) = cluster.alternate_cluster.clone() {
                     let variants = cluster_variants(tree, &alternate_cluster)
                         .ok_or_else(|| eyre!("cluster referenced in `alternateCluster` not found"))?
                         .clone();